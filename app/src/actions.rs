@@ -0,0 +1,218 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::input::{Player, RcadeButton, update_buttons};
+
+/// The control schemes shipped with the game, keyed by scheme name.
+pub const BUNDLED_BINDINGS: &str = include_str!("../bindings.json");
+
+/// Name of the scheme [`ActionMap::default`] loads from [`BUNDLED_BINDINGS`].
+pub const DEFAULT_SCHEME: &str = "default";
+
+/// Turns the buttons held this frame into a `ButtonInput<Action>` resource, using the bindings
+/// in [`ActionMap`].
+pub struct ActionPlugin;
+
+impl Plugin for ActionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ActionMap>()
+            .init_resource::<ButtonInput<Action>>()
+            .add_systems(PreUpdate, update_actions.after(update_buttons));
+    }
+}
+
+/// Something a player can do, independent of the buttons it is bound to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Action {
+    MoveForward,
+    MoveBack,
+    MoveLeft,
+    MoveRight,
+    LookLeft,
+    LookRight,
+    LookUp,
+    LookDown,
+    Confirm,
+    Cancel,
+    /// Switch between one shared camera and a split screen view for each player.
+    ToggleSplitScreen,
+    /// Switch the cameras between flying freely and orbiting the shapes.
+    ToggleOrbit,
+}
+
+impl Action {
+    pub const ALL: [Action; 12] = [
+        Action::MoveForward,
+        Action::MoveBack,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::LookLeft,
+        Action::LookRight,
+        Action::LookUp,
+        Action::LookDown,
+        Action::Confirm,
+        Action::Cancel,
+        Action::ToggleSplitScreen,
+        Action::ToggleOrbit,
+    ];
+}
+
+/// The buttons bound to each [`Action`]. An action is held while any of its buttons is.
+///
+/// Bindings files map scheme names to action maps, each of which maps action names to lists
+/// of [`RcadeButton::name`]s. See `bindings.json` for the schemes bundled with the game.
+#[derive(Resource, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ActionMap {
+    bindings: HashMap<Action, Vec<RcadeButton>>,
+}
+
+impl ActionMap {
+    /// A map with nothing bound.
+    pub fn empty() -> Self {
+        ActionMap {
+            bindings: HashMap::new(),
+        }
+    }
+
+    /// Parses every scheme in a bindings file.
+    pub fn schemes_from_json(json: &str) -> Result<HashMap<String, ActionMap>, ActionMapError> {
+        serde_json::from_str(json).map_err(ActionMapError::Parse)
+    }
+
+    /// Parses the scheme called `scheme` out of a bindings file.
+    pub fn from_json(json: &str, scheme: &str) -> Result<Self, ActionMapError> {
+        Self::schemes_from_json(json)?
+            .remove(scheme)
+            .ok_or_else(|| ActionMapError::UnknownScheme(scheme.to_string()))
+    }
+
+    /// Loads one of the schemes bundled with the game.
+    pub fn bundled(scheme: &str) -> Result<Self, ActionMapError> {
+        Self::from_json(BUNDLED_BINDINGS, scheme)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("action maps always serialize")
+    }
+
+    pub fn bindings(&self, action: Action) -> &[RcadeButton] {
+        self.bindings.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Adds `button` to the buttons that trigger `action`.
+    pub fn bind(&mut self, action: Action, button: RcadeButton) {
+        let bindings = self.bindings.entry(action).or_default();
+
+        if !bindings.contains(&button) {
+            bindings.push(button);
+        }
+    }
+
+    /// Stops `button` from triggering `action`.
+    pub fn unbind(&mut self, action: Action, button: RcadeButton) {
+        if let Some(bindings) = self.bindings.get_mut(&action) {
+            bindings.retain(|bound| *bound != button);
+        }
+    }
+
+    /// Replaces all of the buttons that trigger `action`.
+    pub fn rebind(&mut self, action: Action, buttons: impl IntoIterator<Item = RcadeButton>) {
+        self.bindings.insert(action, Vec::new());
+
+        for button in buttons {
+            self.bind(action, button);
+        }
+    }
+
+    /// A copy of this map with every player-specific button moved over to `player`, so a scheme
+    /// written for player 1 can be played by either.
+    pub fn with_player(&self, player: Player) -> Self {
+        let mut map = ActionMap::empty();
+
+        for (action, buttons) in &self.bindings {
+            map.rebind(
+                *action,
+                buttons.iter().map(|button| match *button {
+                    RcadeButton::Player(_, button) => RcadeButton::Player(player, button),
+                    shared => shared,
+                }),
+            );
+        }
+
+        map
+    }
+
+    /// Whether any of the buttons bound to `action` is held.
+    pub fn is_held(&self, action: Action, buttons: &ButtonInput<RcadeButton>) -> bool {
+        buttons.any_pressed(self.bindings(action).iter().copied())
+    }
+}
+
+impl Default for ActionMap {
+    fn default() -> Self {
+        Self::bundled(DEFAULT_SCHEME).expect("bundled bindings are valid")
+    }
+}
+
+#[derive(Debug)]
+pub enum ActionMapError {
+    /// The bindings file isn't valid JSON, or names an unknown action or button.
+    Parse(serde_json::Error),
+    /// The bindings file has no scheme with this name.
+    UnknownScheme(String),
+}
+
+impl std::fmt::Display for ActionMapError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ActionMapError::Parse(e) => write!(f, "Failed to parse bindings: {e}"),
+            ActionMapError::UnknownScheme(scheme) => {
+                write!(f, "No control scheme named `{scheme}`")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ActionMapError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ActionMapError::Parse(e) => Some(e),
+            ActionMapError::UnknownScheme(_) => None,
+        }
+    }
+}
+
+/// Feeds this frame's buttons into `ButtonInput<Action>` through the [`ActionMap`].
+///
+/// The resource only counts as changed on frames where an action is pressed or released.
+pub fn update_actions(
+    map: Res<ActionMap>,
+    buttons: Res<ButtonInput<RcadeButton>>,
+    mut actions: ResMut<ButtonInput<Action>>,
+) {
+    let mut changed = false;
+
+    let inner = actions.bypass_change_detection();
+    inner.clear();
+
+    for action in Action::ALL {
+        let held = map.is_held(action, &buttons);
+
+        if held != inner.pressed(action) {
+            changed = true;
+
+            if held {
+                inner.press(action);
+            } else {
+                inner.release(action);
+            }
+        }
+    }
+
+    if changed {
+        actions.set_changed();
+    }
+}
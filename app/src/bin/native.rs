@@ -0,0 +1,54 @@
+// Runs the game in a regular desktop window, with the keyboard standing in for the cabinet's
+// controller. See `input::sample_keyboard` for the key layout.
+
+use bevy::{
+    input::InputSystems, light::DirectionalLightShadowMap, log::LogPlugin, prelude::*,
+    window::WindowResolution,
+};
+
+use main::{
+    GamePlugin,
+    config::RcadeConfig,
+    input::{self, ControllerInputSystems},
+};
+
+/// How many screen pixels each pixel of the cabinet's resolution takes up.
+const WINDOW_SCALE: u32 = 3;
+
+fn main() {
+    let config = RcadeConfig::default();
+
+    let (width, height) = config.initial_resolution();
+
+    App::new()
+        .add_plugins(
+            DefaultPlugins
+                .set(WindowPlugin {
+                    primary_window: Some(Window {
+                        title: "rose-sample-rs".to_string(),
+                        resolution: WindowResolution::new(
+                            width * WINDOW_SCALE,
+                            height * WINDOW_SCALE,
+                        ),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                })
+                .set(LogPlugin {
+                    level: config.log_level,
+                    ..Default::default()
+                })
+                .set(ImagePlugin::default_nearest()),
+        )
+        .insert_resource(DirectionalLightShadowMap {
+            size: config.shadow_map_size,
+        })
+        .add_systems(
+            PreUpdate,
+            input::sample_keyboard
+                .in_set(ControllerInputSystems)
+                .after(InputSystems),
+        )
+        .add_plugins(GamePlugin)
+        .run();
+}
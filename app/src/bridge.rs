@@ -0,0 +1,279 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::{HashMap, VecDeque},
+    rc::Rc,
+};
+
+use bevy::prelude::*;
+use js_sys::{Array, JSON};
+use serde::{Serialize, de::DeserializeOwned};
+use wasm_bindgen::{JsCast, JsValue, prelude::Closure};
+use web_sys::{DedicatedWorkerGlobalScope, MessageEvent, MessagePort, console};
+
+use crate::hook::is_plugin_channel;
+
+/// Lets game systems talk to the RCade parent frame without writing any JS.
+///
+/// Messages posted to the worker that aren't part of the host/worker protocol, or the RCade
+/// SDK's `plugin_channel` replies, show up as [`FromParent`] messages, and [`ToParent`]
+/// messages are posted back to the host, which forwards them to the parent frame. Both carry
+/// JSON; anything that has to be transferred, like a `MessagePort`, can be posted through the
+/// [`WorkerMessages`] non-send resource instead.
+///
+/// `MessagePort`s the parent transfers along with a message can't travel in a Bevy message, so
+/// they are kept by [`WorkerMessages`] and taken with [`WorkerMessages::take_ports`]. Messages
+/// whose data can't be represented as JSON are dropped with a warning.
+///
+/// Messages only flow when the embedding app provides [`WorkerMessages`]. Without it, as in the
+/// native runner, [`ToParent`] messages are dropped and no [`FromParent`] messages arrive.
+pub struct MessageBridgePlugin;
+
+impl Plugin for MessageBridgePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<FromParent>()
+            .add_message::<ToParent>()
+            .add_systems(PreUpdate, receive_messages)
+            .add_systems(PostUpdate, send_messages);
+    }
+}
+
+/// A message from the parent frame, forwarded to the worker by the host.
+///
+/// The host only forwards objects with a string `type` field, and drops anything else with a
+/// warning in the page's console.
+#[derive(Message, Clone, Debug, PartialEq)]
+pub struct FromParent {
+    pub data: serde_json::Value,
+    /// The ports transferred along with the message, if there were any.
+    pub ports: Option<TransferId>,
+}
+
+/// Names the `MessagePort`s that came with a [`FromParent`] message, to take them from
+/// [`WorkerMessages::take_ports`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TransferId(u64);
+
+impl FromParent {
+    /// Reads the message as a `T`.
+    pub fn parse<T: DeserializeOwned>(&self) -> Result<T, serde_json::Error> {
+        T::deserialize(&self.data)
+    }
+}
+
+/// A message for the parent frame, posted at the end of the frame.
+///
+/// The host only forwards objects with a string `type` field, like the ones an enum with
+/// `#[serde(tag = "type")]` serializes to. Numbers, strings and objects without a `type` are
+/// dropped with a warning, so [`ToParent::new`] refuses them up front.
+#[derive(Message, Clone, Debug, PartialEq)]
+pub struct ToParent {
+    pub data: serde_json::Value,
+}
+
+impl ToParent {
+    /// Fails if `message` can't be serialized, or isn't shaped like a message the host forwards.
+    pub fn new(message: &impl Serialize) -> Result<Self, serde_json::Error> {
+        let data = serde_json::to_value(message)?;
+
+        check_shape(&data).map_err(|reason| {
+            serde::ser::Error::custom(format!("the host wouldn't forward this message, {reason}"))
+        })?;
+
+        Ok(ToParent { data })
+    }
+}
+
+/// Checks that `data` is an object with a string `type` field, which is what the host expects
+/// of every message that isn't part of the host/worker protocol.
+fn check_shape(data: &serde_json::Value) -> Result<(), &'static str> {
+    match data {
+        serde_json::Value::Object(fields) if fields.get("type").is_some_and(|t| t.is_string()) => {
+            Ok(())
+        }
+        serde_json::Value::Object(_) => Err("it has no string `type` field"),
+        _ => Err("it isn't an object"),
+    }
+}
+
+/// Messages received since the last frame, along with the ports transferred with them.
+type Inbox = Rc<RefCell<VecDeque<(serde_json::Value, Vec<MessagePort>)>>>;
+
+/// The worker's end of `postMessage`, kept as a non-send resource.
+///
+/// Queues incoming messages that aren't part of the host/worker protocol until
+/// [`receive_messages`] turns them into [`FromParent`] messages.
+///
+/// The ports that came with a message are kept for as long as the message can be read, two
+/// frames, and closed if nobody took them by then.
+pub struct WorkerMessages {
+    inbox: Inbox,
+    ports: RefCell<HashMap<TransferId, Vec<MessagePort>>>,
+    next_transfer: Cell<u64>,
+    /// Transfers received by the previous and the latest call to [`WorkerMessages::drain`].
+    received: RefCell<[Vec<TransferId>; 2]>,
+    on_message: Closure<dyn FnMut(MessageEvent)>,
+}
+
+impl WorkerMessages {
+    pub fn install() -> Result<Self, JsValue> {
+        let inbox = Rc::new(RefCell::new(VecDeque::new()));
+
+        let on_message = {
+            let inbox = inbox.clone();
+
+            Closure::wrap(Box::new(move |event: MessageEvent| {
+                // Protocol messages are handled by the integration, and bad ones reported there.
+                // Plugin channels belong to the SDK, which would lose their ports otherwise.
+                if !matches!(protocol::Message::from_js(&event.data()), Ok(None))
+                    || is_plugin_channel(&event.data())
+                {
+                    return;
+                }
+
+                let ports = event
+                    .ports()
+                    .iter()
+                    .filter_map(|port| port.dyn_into::<MessagePort>().ok())
+                    .collect::<Vec<_>>();
+
+                match to_json(&event.data()) {
+                    Ok(data) => inbox.borrow_mut().push_back((data, ports)),
+                    Err(e) => {
+                        console::warn_2(
+                            &format!("Dropped message from parent: {e}").into(),
+                            &event.data(),
+                        );
+
+                        for port in ports {
+                            port.close();
+                        }
+                    }
+                }
+            }) as Box<dyn FnMut(MessageEvent)>)
+        };
+
+        global()
+            .add_event_listener_with_callback("message", on_message.as_ref().unchecked_ref())?;
+
+        Ok(Self {
+            inbox,
+            ports: RefCell::new(HashMap::new()),
+            next_transfer: Cell::new(0),
+            received: RefCell::new([Vec::new(), Vec::new()]),
+            on_message,
+        })
+    }
+
+    /// Takes the ports that were transferred along with a [`FromParent`] message. Each message's
+    /// ports can only be taken once, and only while the message can still be read.
+    pub fn take_ports(&self, transfer: TransferId) -> Vec<MessagePort> {
+        self.ports
+            .borrow_mut()
+            .remove(&transfer)
+            .unwrap_or_default()
+    }
+
+    /// Posts `data` to the parent frame, transferring the objects in `transfer`.
+    pub fn post_with_transfer(&self, data: &JsValue, transfer: &Array) -> Result<(), JsValue> {
+        global().post_message_with_transfer(data, transfer)
+    }
+
+    /// Posts JSON `data` to the parent frame.
+    pub fn post_json(&self, data: &serde_json::Value) -> Result<(), JsValue> {
+        let data = JSON::parse(&data.to_string())?;
+
+        self.post_with_transfer(&data, &Array::new())
+    }
+
+    fn drain(&self) -> Vec<FromParent> {
+        let mut ports = self.ports.borrow_mut();
+        let mut received = self.received.borrow_mut();
+
+        // The messages from two frames ago can't be read any more, so neither can their ports
+        for expired in std::mem::take(&mut received[0]) {
+            for port in ports.remove(&expired).unwrap_or_default() {
+                port.close();
+            }
+        }
+
+        received.swap(0, 1);
+
+        self.inbox
+            .borrow_mut()
+            .drain(..)
+            .map(|(data, transferred)| {
+                if transferred.is_empty() {
+                    return FromParent { data, ports: None };
+                }
+
+                let transfer = TransferId(self.next_transfer.get());
+                self.next_transfer.set(transfer.0 + 1);
+
+                ports.insert(transfer, transferred);
+                received[1].push(transfer);
+
+                FromParent {
+                    data,
+                    ports: Some(transfer),
+                }
+            })
+            .collect()
+    }
+}
+
+impl Drop for WorkerMessages {
+    fn drop(&mut self) {
+        if let Err(e) = global().remove_event_listener_with_callback(
+            "message",
+            self.on_message.as_ref().unchecked_ref(),
+        ) {
+            console::error_2(&"Failed to stop listening for parent messages:".into(), &e);
+        }
+    }
+}
+
+fn global() -> DedicatedWorkerGlobalScope {
+    js_sys::global().unchecked_into()
+}
+
+fn to_json(data: &JsValue) -> Result<serde_json::Value, String> {
+    let text = JSON::stringify(data)
+        .map_err(|e| format!("it can't be converted to JSON ({e:?})"))?
+        .as_string()
+        .ok_or_else(|| "it can't be converted to JSON".to_string())?;
+
+    serde_json::from_str(&text).map_err(|e| e.to_string())
+}
+
+/// Turns the messages received since the last frame into [`FromParent`] messages.
+pub fn receive_messages(
+    worker: Option<NonSend<WorkerMessages>>,
+    mut received: MessageWriter<FromParent>,
+) {
+    if let Some(worker) = worker {
+        received.write_batch(worker.drain());
+    }
+}
+
+/// Posts this frame's [`ToParent`] messages.
+pub fn send_messages(
+    worker: Option<NonSend<WorkerMessages>>,
+    mut outgoing: MessageReader<ToParent>,
+) {
+    let Some(worker) = worker else {
+        outgoing.clear();
+        return;
+    };
+
+    for message in outgoing.read() {
+        // The host would drop it anyway, but only this side knows which system sent it
+        if let Err(reason) = check_shape(&message.data) {
+            warn!("Dropped message to parent, {reason}: {}", message.data);
+            continue;
+        }
+
+        if let Err(e) = worker.post_json(&message.data) {
+            console::error_2(&"Failed to post message to parent:".into(), &e);
+        }
+    }
+}
@@ -0,0 +1,591 @@
+use std::collections::HashMap;
+
+use bevy::{
+    camera::{Viewport, primitives::MeshAabb},
+    prelude::*,
+    window::PrimaryWindow,
+};
+
+use crate::{
+    GROUND_SIZE, SHAPES_CENTRE, Shape,
+    actions::{Action, ActionMap},
+    input::{Player, RcadeButton},
+};
+
+/// Flies the game's cameras around, either as one camera shared by both players or as a split
+/// screen view for each, switched with [`Action::ToggleSplitScreen`].
+///
+/// Cameras either fly freely or orbit the shapes, switched with [`Action::ToggleOrbit`], and are
+/// kept within the [`CameraConstraints`] either way.
+pub struct CameraControlPlugin;
+
+impl Plugin for CameraControlPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CameraMode>()
+            .init_resource::<SplitLayout>()
+            .init_resource::<SplitScreenBindings>()
+            .init_resource::<ControlStyle>()
+            .init_resource::<OrbitSettings>()
+            .init_resource::<CameraConstraints>()
+            .add_systems(
+                Update,
+                (
+                    toggle_camera_mode,
+                    toggle_control_style,
+                    apply_camera_mode,
+                    enter_orbit.run_if(resource_changed::<ControlStyle>),
+                    camera_control_system.run_if(resource_equals(CameraMode::Shared)),
+                    split_camera_control_system.run_if(resource_equals(CameraMode::Split)),
+                    constrain_cameras,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Whether the players share one camera or each get their own half of the screen.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CameraMode {
+    /// Player 1's camera fills the screen and both players steer it through the [`ActionMap`].
+    #[default]
+    Shared,
+    /// Each player's camera gets half of the screen, laid out by [`SplitLayout`], and is steered
+    /// by that player alone through [`SplitScreenBindings`].
+    Split,
+}
+
+/// How the screen is divided in [`CameraMode::Split`].
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SplitLayout {
+    /// Player 1 on the left, player 2 on the right.
+    #[default]
+    SideBySide,
+    /// Player 1 on top, player 2 below.
+    Stacked,
+}
+
+impl SplitLayout {
+    /// The viewports of player 1 and player 2 on a render target of `size` physical pixels.
+    pub fn viewports(self, size: UVec2) -> [Viewport; 2] {
+        let (first, second_position) = match self {
+            SplitLayout::SideBySide => (UVec2::new(size.x / 2, size.y), UVec2::new(size.x / 2, 0)),
+            SplitLayout::Stacked => (UVec2::new(size.x, size.y / 2), UVec2::new(0, size.y / 2)),
+        };
+
+        [
+            Viewport {
+                physical_position: UVec2::ZERO,
+                physical_size: first.max(UVec2::ONE),
+                ..default()
+            },
+            Viewport {
+                physical_position: second_position,
+                physical_size: (size - second_position).max(UVec2::ONE),
+                ..default()
+            },
+        ]
+    }
+}
+
+/// The camera a player looks through. Player 1's is the one used in [`CameraMode::Shared`],
+/// player 2's is only active in [`CameraMode::Split`].
+///
+/// Each keeps its own transform and projection, so changing one doesn't affect the other.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+#[require(Orbit)]
+pub struct PlayerView(pub Player);
+
+/// How the controller moves the cameras.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ControlStyle {
+    /// Move and turn the camera wherever it is pointing.
+    #[default]
+    FreeFly,
+    /// Circle [`OrbitSettings::focus`], always looking at it. Left and right go around it, look
+    /// up and down raise and lower the camera, and forward and back zoom in and out.
+    Orbit,
+}
+
+/// Speeds and limits of [`ControlStyle::Orbit`].
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct OrbitSettings {
+    /// The point the cameras orbit, the middle of the shapes by default.
+    pub focus: Vec3,
+    /// Radians per second around the focus.
+    pub yaw_speed: f32,
+    /// Radians per second up and down.
+    pub pitch_speed: f32,
+    /// How quickly zooming halves or doubles the distance, in doublings per second.
+    pub zoom_speed: f32,
+    /// Lowest and highest angle above the focus, in radians.
+    pub pitch_range: (f32, f32),
+    /// Closest and furthest distance from the focus.
+    pub distance_range: (f32, f32),
+    /// How quickly the camera catches up with the controller, per second. Higher is snappier.
+    pub damping: f32,
+}
+
+impl Default for OrbitSettings {
+    fn default() -> Self {
+        Self {
+            focus: SHAPES_CENTRE,
+            yaw_speed: 1.5,
+            pitch_speed: 1.0,
+            zoom_speed: 1.0,
+            pitch_range: (0.05, 1.4),
+            distance_range: (3.0, 30.0),
+            damping: 8.0,
+        }
+    }
+}
+
+/// Limits on where the cameras can go, applied after they are steered.
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct CameraConstraints {
+    /// Lowest and highest angle above the horizon the camera can look, in radians, so it can't
+    /// flip over.
+    pub pitch_range: (f32, f32),
+    /// How close the camera can get to the ground.
+    pub min_height: f32,
+    /// The space the camera has to stay inside, or `None` to let it go anywhere.
+    pub bounds: Option<CameraBounds>,
+    /// Whether the camera is kept out of the bounding spheres of the [`Shape`]s.
+    pub collide_with_shapes: bool,
+    /// How far the camera stays from the shapes it collides with.
+    pub radius: f32,
+}
+
+impl Default for CameraConstraints {
+    /// Keeps the camera above the ground plane and within its edges.
+    fn default() -> Self {
+        let half = GROUND_SIZE / 2.0;
+
+        Self {
+            pitch_range: (-1.4, 1.4),
+            min_height: 0.5,
+            bounds: Some(CameraBounds::Box {
+                min: Vec3::new(-half, 0.0, -half),
+                max: Vec3::splat(half),
+            }),
+            collide_with_shapes: true,
+            radius: 0.25,
+        }
+    }
+}
+
+/// A volume the camera has to stay inside.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CameraBounds {
+    /// An axis-aligned box between two corners.
+    Box {
+        min: Vec3,
+        max: Vec3,
+    },
+    Sphere {
+        centre: Vec3,
+        radius: f32,
+    },
+}
+
+impl CameraBounds {
+    /// The closest point to `point` inside the bounds.
+    pub fn clamp(self, point: Vec3) -> Vec3 {
+        match self {
+            CameraBounds::Box { min, max } => point.clamp(min, max),
+            CameraBounds::Sphere { centre, radius } => {
+                centre + (point - centre).clamp_length_max(radius)
+            }
+        }
+    }
+}
+
+/// A camera's position around [`OrbitSettings::focus`], kept on every [`PlayerView`].
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq)]
+pub struct Orbit {
+    /// Where the controller is steering the camera.
+    pub target: OrbitAngles,
+    /// Where the camera is, easing towards `target`.
+    pub current: OrbitAngles,
+}
+
+/// A point around the focus, in spherical coordinates.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct OrbitAngles {
+    /// Radians around the Y axis, 0 being on the +Z side of the focus.
+    pub yaw: f32,
+    /// Radians above the horizon.
+    pub pitch: f32,
+    pub distance: f32,
+}
+
+impl OrbitAngles {
+    /// The angles of the point `offset` away from the focus.
+    pub fn from_offset(offset: Vec3) -> Self {
+        let distance = offset.length();
+
+        if distance <= f32::EPSILON {
+            return Self::default();
+        }
+
+        Self {
+            yaw: offset.x.atan2(offset.z),
+            pitch: (offset.y / distance).clamp(-1.0, 1.0).asin(),
+            distance,
+        }
+    }
+
+    /// The offset from the focus of the point at these angles.
+    pub fn offset(self) -> Vec3 {
+        let (yaw_sin, yaw_cos) = self.yaw.sin_cos();
+        let (pitch_sin, pitch_cos) = self.pitch.sin_cos();
+
+        Vec3::new(yaw_sin * pitch_cos, pitch_sin, yaw_cos * pitch_cos) * self.distance
+    }
+
+    /// These angles with pitch and distance kept within `settings`' limits.
+    pub fn clamped(self, settings: &OrbitSettings) -> Self {
+        Self {
+            yaw: self.yaw,
+            pitch: self
+                .pitch
+                .clamp(settings.pitch_range.0, settings.pitch_range.1),
+            distance: self
+                .distance
+                .clamp(settings.distance_range.0, settings.distance_range.1),
+        }
+    }
+
+    fn lerp(self, other: Self, t: f32) -> Self {
+        Self {
+            yaw: self.yaw.lerp(other.yaw, t),
+            pitch: self.pitch.lerp(other.pitch, t),
+            distance: self.distance.lerp(other.distance, t),
+        }
+    }
+}
+
+/// What each player's stick and buttons do in [`CameraMode::Split`].
+///
+/// Defaults to the bundled `one_stick` scheme for each player, which moves with up and down,
+/// turns with left and right, and looks up and down with A and B.
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct SplitScreenBindings {
+    pub player1: ActionMap,
+    pub player2: ActionMap,
+}
+
+impl SplitScreenBindings {
+    pub fn get(&self, player: Player) -> &ActionMap {
+        match player {
+            Player::One => &self.player1,
+            Player::Two => &self.player2,
+        }
+    }
+}
+
+impl Default for SplitScreenBindings {
+    fn default() -> Self {
+        let one_stick = ActionMap::bundled("one_stick").expect("bundled bindings are valid");
+
+        Self {
+            player1: one_stick.with_player(Player::One),
+            player2: one_stick.with_player(Player::Two),
+        }
+    }
+}
+
+pub fn toggle_camera_mode(actions: Res<ButtonInput<Action>>, mut mode: ResMut<CameraMode>) {
+    if actions.just_pressed(Action::ToggleSplitScreen) {
+        *mode = match *mode {
+            CameraMode::Shared => CameraMode::Split,
+            CameraMode::Split => CameraMode::Shared,
+        };
+    }
+}
+
+pub fn toggle_control_style(actions: Res<ButtonInput<Action>>, mut style: ResMut<ControlStyle>) {
+    if actions.just_pressed(Action::ToggleOrbit) {
+        *style = match *style {
+            ControlStyle::FreeFly => ControlStyle::Orbit,
+            ControlStyle::Orbit => ControlStyle::FreeFly,
+        };
+    }
+}
+
+/// Starts each camera's orbit from wherever it is when switching to [`ControlStyle::Orbit`], so
+/// it eases into place instead of jumping.
+pub fn enter_orbit(
+    style: Res<ControlStyle>,
+    settings: Res<OrbitSettings>,
+    mut cameras: Query<(&Transform, &mut Orbit)>,
+) {
+    if *style != ControlStyle::Orbit {
+        return;
+    }
+
+    for (transform, mut orbit) in &mut cameras {
+        let current = OrbitAngles::from_offset(transform.translation - settings.focus);
+
+        *orbit = Orbit {
+            target: current.clamped(&settings),
+            current,
+        };
+    }
+}
+
+/// Turns player 2's camera on and off with the [`CameraMode`], and keeps both cameras' viewports
+/// in line with the [`SplitLayout`] and the size of the window.
+pub fn apply_camera_mode(
+    mode: Res<CameraMode>,
+    layout: Res<SplitLayout>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut cameras: Query<(&mut Camera, &PlayerView)>,
+) {
+    let split = *mode == CameraMode::Split;
+
+    let viewports = windows
+        .single()
+        .ok()
+        .filter(|_| split)
+        .map(|window| layout.viewports(window.physical_size()));
+
+    for (mut camera, view) in &mut cameras {
+        let is_active = split || view.0 == Player::One;
+
+        let viewport = viewports.as_ref().map(|viewports| match view.0 {
+            Player::One => viewports[0].clone(),
+            Player::Two => viewports[1].clone(),
+        });
+
+        // Only touch the camera when something changed, so change detection stays meaningful
+        if camera.is_active != is_active {
+            camera.is_active = is_active;
+        }
+
+        if !same_viewport(camera.viewport.as_ref(), viewport.as_ref()) {
+            camera.viewport = viewport;
+        }
+    }
+}
+
+fn same_viewport(a: Option<&Viewport>, b: Option<&Viewport>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => {
+            a.physical_position == b.physical_position
+                && a.physical_size == b.physical_size
+                && a.depth == b.depth
+        }
+        (a, b) => a.is_none() && b.is_none(),
+    }
+}
+
+/// Moves player 1's camera in [`CameraMode::Shared`], with actions from both players.
+pub fn camera_control_system(
+    actions: Res<ButtonInput<Action>>,
+
+    style: Res<ControlStyle>,
+
+    settings: Res<OrbitSettings>,
+
+    mut camera_query: Query<(&mut Transform, &mut Orbit, &PlayerView)>,
+
+    time: Res<Time>,
+) {
+    if let Some((mut transform, mut orbit, _)) = camera_query
+        .iter_mut()
+        .find(|(_, _, view)| view.0 == Player::One)
+    {
+        let held = |action| actions.pressed(action);
+
+        match *style {
+            ControlStyle::FreeFly => fly(&mut transform, held, &time),
+            ControlStyle::Orbit => circle(&mut transform, &mut orbit, held, &settings, &time),
+        }
+    }
+}
+
+/// Moves each player's camera in [`CameraMode::Split`], with that player's buttons only.
+pub fn split_camera_control_system(
+    bindings: Res<SplitScreenBindings>,
+
+    buttons: Res<ButtonInput<RcadeButton>>,
+
+    style: Res<ControlStyle>,
+
+    settings: Res<OrbitSettings>,
+
+    mut camera_query: Query<(&mut Transform, &mut Orbit, &PlayerView)>,
+
+    time: Res<Time>,
+) {
+    for (mut transform, mut orbit, view) in &mut camera_query {
+        let map = bindings.get(view.0);
+        let held = |action| map.is_held(action, &buttons);
+
+        match *style {
+            ControlStyle::FreeFly => fly(&mut transform, held, &time),
+            ControlStyle::Orbit => circle(&mut transform, &mut orbit, held, &settings, &time),
+        }
+    }
+}
+
+/// Steers `orbit` by the camera actions that are `held`, and eases `transform` after it.
+fn circle(
+    transform: &mut Transform,
+    orbit: &mut Orbit,
+    held: impl Fn(Action) -> bool,
+    settings: &OrbitSettings,
+    time: &Time,
+) {
+    let delta = time.delta_secs();
+
+    let axis =
+        |negative: Action, positive: Action| f32::from(held(positive)) - f32::from(held(negative));
+
+    let around =
+        axis(Action::MoveLeft, Action::MoveRight) + axis(Action::LookLeft, Action::LookRight);
+    let up = axis(Action::LookDown, Action::LookUp);
+    let zoom = axis(Action::MoveForward, Action::MoveBack);
+
+    let mut target = orbit.target;
+    target.yaw += around.clamp(-1.0, 1.0) * settings.yaw_speed * delta;
+    target.pitch += up * settings.pitch_speed * delta;
+    target.distance *= (zoom * settings.zoom_speed * delta).exp2();
+    orbit.target = target.clamped(settings);
+
+    // Framerate independent exponential smoothing, shared by position and rotation
+    let ease = 1.0 - (-settings.damping * delta).exp();
+
+    orbit.current = orbit.current.lerp(orbit.target, ease);
+
+    transform.translation = settings.focus + orbit.current.offset();
+
+    let facing = transform.looking_at(settings.focus, Vec3::Y).rotation;
+    transform.rotation = transform.rotation.slerp(facing, ease);
+}
+
+/// Moves and turns `transform` by the camera actions that are `held`.
+fn fly(transform: &mut Transform, held: impl Fn(Action) -> bool, time: &Time) {
+    let move_speed = 5.0 * time.delta_secs();
+
+    let rotate_speed = 2.0 * time.delta_secs();
+
+    // Movement (WASD-style), on player 1's stick by default
+
+    let forward = transform.forward();
+
+    let right = transform.right();
+
+    if held(Action::MoveForward) {
+        transform.translation += forward * move_speed;
+    }
+
+    if held(Action::MoveBack) {
+        transform.translation -= forward * move_speed;
+    }
+
+    if held(Action::MoveLeft) {
+        transform.translation -= right * move_speed;
+    }
+
+    if held(Action::MoveRight) {
+        transform.translation += right * move_speed;
+    }
+
+    // Rotation (look around), on player 2's stick by default
+
+    if held(Action::LookLeft) {
+        transform.rotate_y(rotate_speed);
+    }
+
+    if held(Action::LookRight) {
+        transform.rotate_y(-rotate_speed);
+    }
+
+    if held(Action::LookUp) {
+        transform.rotate_local_x(rotate_speed);
+    }
+
+    if held(Action::LookDown) {
+        transform.rotate_local_x(-rotate_speed);
+    }
+}
+
+type ShapeMesh<'a> = (&'a Transform, &'a Mesh3d);
+
+/// Applies the [`CameraConstraints`] to every camera, once it has been moved for the frame.
+pub fn constrain_cameras(
+    constraints: Res<CameraConstraints>,
+
+    meshes: Res<Assets<Mesh>>,
+
+    shapes: Query<ShapeMesh, (With<Shape>, Without<PlayerView>)>,
+
+    mut cameras: Query<&mut Transform, With<PlayerView>>,
+
+    // Bounding sphere of each mesh in its own space, as computing one walks every vertex
+    mut mesh_bounds: Local<HashMap<AssetId<Mesh>, (Vec3, f32)>>,
+) {
+    let obstacles = if constraints.collide_with_shapes {
+        shapes
+            .iter()
+            .filter_map(|(transform, mesh)| {
+                let (centre, radius) = match mesh_bounds.get(&mesh.id()) {
+                    Some(&bounds) => bounds,
+                    None => {
+                        let aabb = meshes.get(mesh)?.compute_aabb()?;
+                        let bounds = (aabb.center.into(), aabb.half_extents.length());
+
+                        mesh_bounds.insert(mesh.id(), bounds);
+
+                        bounds
+                    }
+                };
+
+                Some((
+                    transform.transform_point(centre),
+                    radius * transform.scale.max_element(),
+                ))
+            })
+            .collect::<Vec<_>>()
+    } else {
+        Vec::new()
+    };
+
+    for mut transform in &mut cameras {
+        // Only write what changes, so an unconstrained camera keeps its exact transform
+        if let Some(rotation) = clamp_pitch(transform.rotation, constraints.pitch_range) {
+            transform.rotation = rotation;
+        }
+
+        let mut translation = transform.translation;
+
+        for &(centre, radius) in &obstacles {
+            let reach = radius + constraints.radius;
+            let offset = translation - centre;
+
+            if offset.length_squared() < reach * reach {
+                translation = centre + offset.try_normalize().unwrap_or(Vec3::Y) * reach;
+            }
+        }
+
+        if let Some(bounds) = constraints.bounds {
+            translation = bounds.clamp(translation);
+        }
+
+        translation.y = translation.y.max(constraints.min_height);
+
+        if translation != transform.translation {
+            transform.translation = translation;
+        }
+    }
+}
+
+/// `rotation` with its pitch brought within `range`, or `None` if it already is.
+fn clamp_pitch(rotation: Quat, (min, max): (f32, f32)) -> Option<Quat> {
+    let (yaw, pitch, roll) = rotation.to_euler(EulerRot::YXZ);
+
+    let clamped = pitch.clamp(min, max);
+
+    (clamped != pitch).then(|| Quat::from_euler(EulerRot::YXZ, yaw, clamped, roll))
+}
@@ -0,0 +1,143 @@
+use std::time::Duration;
+
+use bevy::{log::Level, prelude::*};
+
+use crate::hook::{
+    BackendPreference, ContextAttributes, DEFAULT_RESOLUTION, HostScaling, ResolutionPolicy,
+};
+
+/// Everything the RCade integration lets a game tune, passed to [`run`](crate::run) in the
+/// browser, or to
+/// [`RcadePluginExt::with_rcade_config`](crate::hook::RcadePluginExt::with_rcade_config).
+///
+/// The defaults match what [`RcadePluginExt::with_rcade`](crate::hook::RcadePluginExt::with_rcade)
+/// uses, so a game only needs to override what it cares about:
+///
+/// ```
+/// # use bevy::log::Level;
+/// # use main::{config::RcadeConfig, hook::ResolutionPolicy};
+/// let config = RcadeConfig::default()
+///     .with_resolution(ResolutionPolicy::MatchDisplay)
+///     .with_log_level(Level::INFO);
+/// ```
+#[derive(Resource, Clone, Debug)]
+pub struct RcadeConfig {
+    pub resolution: ResolutionPolicy,
+    pub scaling: HostScaling,
+    pub backend: BackendPreference,
+    /// Used to pick a GPU adapter. The WebGL2 context's `powerPreference` is set through
+    /// [`ContextAttributes::power_preference`].
+    pub power_preference: wgpu::PowerPreference,
+    /// Only used by the WebGL2 backend.
+    pub gles_minor_version: wgpu::Gles3MinorVersion,
+    /// Device limits to request. `None` picks limits suited to the chosen backend.
+    pub required_limits: Option<wgpu::Limits>,
+    pub log_level: Level,
+    pub shadow_map_size: usize,
+    /// Attributes the WebGL2 context is created with.
+    pub context_attributes: ContextAttributes,
+    /// Frame rate the frame loop won't exceed. Frames are run on `requestAnimationFrame`, so
+    /// this is best set to the display's refresh rate or an even fraction of it. Must be finite
+    /// and above zero.
+    pub target_fps: f64,
+    /// How often the worker tells the host it is still running. The host's watchdog should
+    /// wait a good few of these before deciding the app is stuck.
+    pub heartbeat_interval: Duration,
+}
+
+impl Default for RcadeConfig {
+    fn default() -> Self {
+        Self {
+            resolution: ResolutionPolicy::default(),
+            scaling: HostScaling::default(),
+            backend: BackendPreference::default(),
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            gles_minor_version: wgpu::Gles3MinorVersion::Version0,
+            required_limits: None,
+            log_level: Level::WARN,
+            shadow_map_size: 512,
+            context_attributes: ContextAttributes::default(),
+            target_fps: 60.0,
+            heartbeat_interval: Duration::from_secs(1),
+        }
+    }
+}
+
+impl RcadeConfig {
+    pub fn with_resolution(mut self, resolution: ResolutionPolicy) -> Self {
+        self.resolution = resolution;
+        self
+    }
+
+    pub fn with_scaling(mut self, scaling: HostScaling) -> Self {
+        self.scaling = scaling;
+        self
+    }
+
+    pub fn with_backend(mut self, backend: BackendPreference) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    pub fn with_power_preference(mut self, power_preference: wgpu::PowerPreference) -> Self {
+        self.power_preference = power_preference;
+        self
+    }
+
+    pub fn with_gles_minor_version(mut self, gles_minor_version: wgpu::Gles3MinorVersion) -> Self {
+        self.gles_minor_version = gles_minor_version;
+        self
+    }
+
+    pub fn with_required_limits(mut self, required_limits: wgpu::Limits) -> Self {
+        self.required_limits = Some(required_limits);
+        self
+    }
+
+    pub fn with_log_level(mut self, log_level: Level) -> Self {
+        self.log_level = log_level;
+        self
+    }
+
+    pub fn with_shadow_map_size(mut self, shadow_map_size: usize) -> Self {
+        self.shadow_map_size = shadow_map_size;
+        self
+    }
+
+    pub fn with_context_attributes(mut self, context_attributes: ContextAttributes) -> Self {
+        self.context_attributes = context_attributes;
+        self
+    }
+
+    pub fn with_desynchronized(mut self, desynchronized: bool) -> Self {
+        self.context_attributes.desynchronized = desynchronized;
+        self
+    }
+
+    /// # Panics
+    ///
+    /// If `target_fps` isn't a finite number above zero.
+    pub fn with_target_fps(mut self, target_fps: f64) -> Self {
+        assert!(
+            target_fps.is_finite() && target_fps > 0.0,
+            "target frame rate must be finite and above zero, got {target_fps}"
+        );
+
+        self.target_fps = target_fps;
+        self
+    }
+
+    pub fn with_heartbeat_interval(mut self, heartbeat_interval: Duration) -> Self {
+        self.heartbeat_interval = heartbeat_interval;
+        self
+    }
+
+    /// Initial resolution of the primary window.
+    pub fn initial_resolution(&self) -> (u32, u32) {
+        match self.resolution {
+            ResolutionPolicy::Fixed { width, height } => (width, height),
+            // Corrected by `apply_display_size` once the canvas size is known
+            ResolutionPolicy::MatchDisplay => DEFAULT_RESOLUTION,
+        }
+    }
+}
@@ -0,0 +1,333 @@
+use std::{
+    any::TypeId,
+    sync::{
+        Arc,
+        atomic::{AtomicU8, Ordering},
+    },
+};
+
+use bevy::{
+    asset::Asset,
+    image::{CompressedImageFormatSupport, TextureAtlasLayout},
+    prelude::*,
+    render::{
+        RenderApp,
+        renderer::{RenderAdapter, RenderAdapterInfo, RenderDevice, RenderQueue},
+        storage::ShaderStorageBuffer,
+        sync_world::{RenderEntity, SyncToRenderWorld},
+    },
+    shader::Shader,
+    time::create_time_channels,
+};
+use wasm_bindgen::{JsCast, JsValue, prelude::Closure};
+use web_sys::{Event, OffscreenCanvas, console};
+
+use crate::hook::{RcadeBackend, RcadeInitError};
+
+/// Registers the [`RenderContextLost`] and [`RenderContextRestored`] messages, and the asset
+/// types Bevy renders with for [`resync_render_world`].
+///
+/// A game with its own `MaterialPlugin`, or any other `RenderAssetPlugin`, registers that asset
+/// type too with [`ResyncAppExt::resync_render_asset`].
+pub struct RenderContextPlugin;
+
+impl Plugin for RenderContextPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<RenderContextLost>()
+            .add_message::<RenderContextRestored>()
+            .init_resource::<ResyncedAssets>()
+            .resync_render_asset::<Shader>()
+            .resync_render_asset::<Image>()
+            .resync_render_asset::<Mesh>()
+            .resync_render_asset::<ShaderStorageBuffer>()
+            .resync_render_asset::<StandardMaterial>()
+            .resync_render_asset::<ColorMaterial>()
+            .resync_render_asset::<TextureAtlasLayout>();
+    }
+}
+
+/// Sent when the WebGL2 context or the WebGPU device is lost, usually because the GPU was reset.
+///
+/// Nothing is rendered until the context is restored, but the game keeps running.
+#[derive(Message, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RenderContextLost;
+
+/// Sent once rendering works again after the context was lost.
+///
+/// Only the render world is rebuilt on a restored context. The game's entities and resources
+/// are kept, and its assets are uploaded again on their own, so most games can ignore this.
+#[derive(Message, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RenderContextRestored;
+
+/// Where the canvas's rendering context is in a loss and restore cycle.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ContextState {
+    #[default]
+    Ok,
+    Lost,
+    /// The context can be used again, and rendering has to be rebuilt on it.
+    Restored,
+}
+
+/// A [`ContextState`] that wgpu's device lost callback, which has to be `Send`, can set too.
+#[derive(Clone, Default)]
+struct SharedState(Arc<AtomicU8>);
+
+impl SharedState {
+    fn get(&self) -> ContextState {
+        match self.0.load(Ordering::Relaxed) {
+            1 => ContextState::Lost,
+            2 => ContextState::Restored,
+            _ => ContextState::Ok,
+        }
+    }
+
+    fn set(&self, state: ContextState) {
+        self.0.store(state as u8, Ordering::Relaxed);
+    }
+}
+
+/// Follows the canvas's rendering context through being lost and restored.
+///
+/// The WebGL2 backend reports this with `webglcontextlost` and `webglcontextrestored` on the
+/// OffscreenCanvas. WebGPU reports a lost device through wgpu instead, see
+/// [`ContextLossListener::watch_device`].
+pub struct ContextLossListener {
+    canvas: OffscreenCanvas,
+    state: SharedState,
+    on_lost: Closure<dyn FnMut(Event)>,
+    on_restored: Closure<dyn FnMut(Event)>,
+}
+
+impl ContextLossListener {
+    pub fn install(canvas: &OffscreenCanvas) -> Result<Self, JsValue> {
+        let state = SharedState::default();
+
+        let on_lost = {
+            let state = state.clone();
+
+            Closure::wrap(Box::new(move |event: Event| {
+                // Without this the browser never tries to restore the context
+                event.prevent_default();
+
+                warn!("WebGL2 context lost, rendering is suspended");
+                state.set(ContextState::Lost);
+            }) as Box<dyn FnMut(Event)>)
+        };
+
+        let on_restored = {
+            let state = state.clone();
+
+            Closure::wrap(Box::new(move |_: Event| {
+                info!("WebGL2 context restored, rebuilding rendering");
+                state.set(ContextState::Restored);
+            }) as Box<dyn FnMut(Event)>)
+        };
+
+        canvas.add_event_listener_with_callback(
+            "webglcontextlost",
+            on_lost.as_ref().unchecked_ref(),
+        )?;
+        canvas.add_event_listener_with_callback(
+            "webglcontextrestored",
+            on_restored.as_ref().unchecked_ref(),
+        )?;
+
+        Ok(Self {
+            canvas: canvas.clone(),
+            state,
+            on_lost,
+            on_restored,
+        })
+    }
+
+    pub fn state(&self) -> ContextState {
+        self.state.get()
+    }
+
+    /// Reports `device` being lost as the context being lost, for the WebGPU backend.
+    pub fn watch_device(&self, device: &wgpu::Device) {
+        let state = self.state.clone();
+
+        device.set_device_lost_callback(move |reason, message| {
+            // Letting go of the device, as when it is replaced, isn't a loss
+            if reason == wgpu::DeviceLostReason::Destroyed {
+                return;
+            }
+
+            console::warn_1(
+                &format!("WebGPU device lost, rendering is suspended: {message}").into(),
+            );
+            state.set(ContextState::Lost);
+        });
+    }
+
+    /// Called once the game knows a WebGPU device was lost. Unlike a WebGL2 context, there's no
+    /// restore to wait for, since a new device can be asked for straight away.
+    pub fn mark_restored(&self) {
+        self.state.set(ContextState::Restored);
+    }
+
+    /// Called once rendering has been rebuilt on the restored context.
+    pub fn mark_rebuilt(&self) {
+        self.state.set(ContextState::Ok);
+    }
+}
+
+impl Drop for ContextLossListener {
+    fn drop(&mut self) {
+        let removed = self
+            .canvas
+            .remove_event_listener_with_callback(
+                "webglcontextlost",
+                self.on_lost.as_ref().unchecked_ref(),
+            )
+            .and_then(|()| {
+                self.canvas.remove_event_listener_with_callback(
+                    "webglcontextrestored",
+                    self.on_restored.as_ref().unchecked_ref(),
+                )
+            });
+
+        if let Err(e) = removed {
+            console::error_2(&"Failed to stop listening for context loss:".into(), &e);
+        }
+    }
+}
+
+/// Takes the render world out of `app`, along with the main world's handles on its device, so
+/// the device is let go of before a new one is asked for.
+pub fn release_render_world(app: &mut App) {
+    if let Some(mut old) = app.remove_sub_app(RenderApp) {
+        // Hands over what changed while the context was lost, so the records of entities
+        // despawned in the meantime don't reach the new render world
+        old.extract(app.world_mut());
+    }
+
+    let world = app.world_mut();
+    world.remove_resource::<RenderDevice>();
+    world.remove_resource::<RenderQueue>();
+    world.remove_resource::<RenderAdapter>();
+    world.remove_resource::<RenderAdapterInfo>();
+}
+
+/// Gives `app` the render world of `renderer`, a throwaway app with the same rendering plugins
+/// that was built and finished on a new device, and tells the game with
+/// [`RenderContextRestored`].
+///
+/// The main world is kept as it is, so the game carries on where it left off. Its entities and
+/// assets are extracted to the new render world again, see [`resync_render_world`].
+pub fn adopt_render_world(app: &mut App, mut renderer: App) -> Result<(), RcadeInitError> {
+    let mut render_app = renderer
+        .remove_sub_app(RenderApp)
+        .ok_or(RcadeInitError::MissingRenderWorld)?;
+
+    move_resource::<RenderDevice>(&mut renderer, app);
+    move_resource::<RenderQueue>(&mut renderer, app);
+    move_resource::<RenderAdapter>(&mut renderer, app);
+    move_resource::<RenderAdapterInfo>(&mut renderer, app);
+    move_resource::<CompressedImageFormatSupport>(&mut renderer, app);
+    move_resource::<RcadeBackend>(&mut renderer, app);
+
+    // The render world loads its shaders through the asset server, and has to find them in
+    // the game's assets rather than the throwaway app's
+    if let Some(asset_server) = app.world().get_resource::<AssetServer>() {
+        render_app.insert_resource(asset_server.clone());
+    }
+
+    let (time_sender, time_receiver) = create_time_channels();
+    render_app.insert_resource(time_sender);
+    app.insert_resource(time_receiver);
+
+    app.insert_sub_app(RenderApp, render_app);
+
+    resync_render_world(app.world_mut());
+
+    app.world_mut().write_message(RenderContextRestored);
+
+    Ok(())
+}
+
+fn move_resource<R: Resource>(from: &mut App, to: &mut App) {
+    if let Some(resource) = from.world_mut().remove_resource::<R>() {
+        to.insert_resource(resource);
+    }
+}
+
+/// Readies the main world for a render world built on a new device.
+///
+/// Every synced entity is queued to be synced again, and every asset type registered with
+/// [`ResyncAppExt::resync_render_asset`] is marked as modified, so the next extraction sends all
+/// of it to the new render world. Assets that were only kept in the render world, see
+/// [`RenderAssetUsages`], can't be uploaded again.
+///
+/// [`RenderAssetUsages`]: bevy::asset::RenderAssetUsages
+pub fn resync_render_world(world: &mut World) {
+    let synced = world
+        .query_filtered::<Entity, With<RenderEntity>>()
+        .iter(world)
+        .collect::<Vec<_>>();
+
+    for entity in synced {
+        let mut entity = world.entity_mut(entity);
+
+        // Without a `RenderEntity` the marker goes without despawning anything, and adding it
+        // back queues the entity like a newly spawned one
+        entity.remove::<RenderEntity>();
+        entity.remove::<SyncToRenderWorld>();
+        entity.insert(SyncToRenderWorld);
+    }
+
+    let resynced = world
+        .get_resource::<ResyncedAssets>()
+        .map(|resynced| resynced.types.clone())
+        .unwrap_or_default();
+
+    for (_, mark_modified) in resynced {
+        mark_modified(world);
+    }
+}
+
+/// The asset types [`resync_render_world`] uploads again, registered with
+/// [`ResyncAppExt::resync_render_asset`].
+#[derive(Resource, Default)]
+pub struct ResyncedAssets {
+    types: Vec<(TypeId, MarkModified)>,
+}
+
+type MarkModified = fn(&mut World);
+
+impl ResyncedAssets {
+    pub fn contains<A: Asset>(&self) -> bool {
+        self.types.iter().any(|(id, _)| *id == TypeId::of::<A>())
+    }
+}
+
+pub trait ResyncAppExt {
+    /// Has the assets of type `A` uploaded again to the render world built on a restored
+    /// context. Every asset type with a `RenderAssetPlugin`, like the one a `MaterialPlugin`
+    /// adds, needs this, or its assets go missing from the screen after a context loss.
+    fn resync_render_asset<A: Asset>(&mut self) -> &mut Self;
+}
+
+impl ResyncAppExt for App {
+    fn resync_render_asset<A: Asset>(&mut self) -> &mut Self {
+        let mut resynced = self
+            .world_mut()
+            .get_resource_or_insert_with(ResyncedAssets::default);
+
+        if !resynced.contains::<A>() {
+            resynced.types.push((TypeId::of::<A>(), mark_modified::<A>));
+        }
+
+        self
+    }
+}
+
+fn mark_modified<A: Asset>(world: &mut World) {
+    if let Some(mut assets) = world.get_resource_mut::<Assets<A>>() {
+        // Mutable access is what queues a `Modified` event for each one
+        for _ in assets.iter_mut() {}
+    }
+}
@@ -0,0 +1,55 @@
+use std::panic::PanicHookInfo;
+
+use protocol::ErrorKind;
+use wasm_bindgen::prelude::*;
+use web_sys::console;
+
+use crate::hook::post_to_host;
+
+#[wasm_bindgen]
+extern "C" {
+    type StackTrace;
+
+    #[wasm_bindgen(js_name = Error, constructor)]
+    fn new() -> StackTrace;
+
+    #[wasm_bindgen(method, getter)]
+    fn stack(this: &StackTrace) -> String;
+}
+
+/// Reports panics to the host as `ERROR` messages, on top of logging them to the console.
+///
+/// Nobody watches the worker console on a cabinet, so the host shows the report over the canvas
+/// and passes it on to the RCade parent frame.
+pub fn install_panic_hook() {
+    std::panic::set_hook(Box::new(|info| {
+        console_error_panic_hook::hook(info);
+
+        if let Err(e) = post_to_host(&panic_message(info)) {
+            console::error_2(&"Failed to report panic to host:".into(), &e);
+        }
+    }));
+}
+
+fn panic_message(info: &PanicHookInfo) -> protocol::Message {
+    let message = info.payload_as_str().unwrap_or("Box<dyn Any>").to_string();
+
+    let location = info.location().map(|location| {
+        format!(
+            "{}:{}:{}",
+            location.file(),
+            location.line(),
+            location.column()
+        )
+    });
+
+    // Rust can't capture backtraces on wasm, but the JS stack covers the same frames
+    let backtrace = Some(StackTrace::new().stack()).filter(|stack| !stack.is_empty());
+
+    protocol::Message::Error {
+        kind: ErrorKind::Panic,
+        message,
+        location,
+        backtrace,
+    }
+}
@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+
+use bevy::{camera::RenderTarget, prelude::*, window::WindowRef};
+use protocol::NamedCanvas;
+
+use crate::hook::RcadeBackend;
+
+/// Names the canvas a window renders to.
+///
+/// The primary window always renders to the main canvas. Any other window needs the name of
+/// one of the host's secondary display canvases, and is skipped without one.
+#[derive(Component, Clone, Debug, PartialEq, Eq)]
+pub struct CanvasName(pub String);
+
+/// The host's canvases for secondary displays, kept as a non-send resource.
+#[derive(Clone, Debug, Default)]
+pub struct SecondaryCanvases(pub Vec<NamedCanvas>);
+
+impl SecondaryCanvases {
+    pub fn get(&self, name: &str) -> Option<&NamedCanvas> {
+        self.0.iter().find(|display| display.name == name)
+    }
+}
+
+/// The window of each secondary display, by name, so cameras can render to them:
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use main::display::Displays;
+/// fn spawn_marquee_camera(mut commands: Commands, displays: Option<Res<Displays>>) {
+///     if let Some(target) = displays.and_then(|displays| displays.target("marquee")) {
+///         commands.spawn((Camera2d, Camera { target, ..default() }));
+///     }
+/// }
+/// ```
+///
+/// Only set up by the browser build, where it is filled in before the `Startup` schedule.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct Displays {
+    windows: HashMap<String, Entity>,
+}
+
+impl Displays {
+    pub fn window(&self, name: &str) -> Option<Entity> {
+        self.windows.get(name).copied()
+    }
+
+    /// Render target for a camera drawing to the display called `name`.
+    pub fn target(&self, name: &str) -> Option<RenderTarget> {
+        self.window(name)
+            .map(|window| RenderTarget::Window(WindowRef::Entity(window)))
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.windows.keys().map(String::as_str)
+    }
+}
+
+/// Spawns a window for each secondary display canvas.
+///
+/// A WebGL2 device can only draw to the canvas it was created on, so secondary displays need
+/// the WebGPU backend and are left blank with WebGL2.
+pub fn spawn_display_windows(
+    mut commands: Commands,
+    canvases: NonSend<SecondaryCanvases>,
+    backend: Res<RcadeBackend>,
+    mut displays: ResMut<Displays>,
+) {
+    for secondary in &canvases.0 {
+        if *backend == RcadeBackend::WebGl2 {
+            warn!(
+                "Display `{}` needs WebGPU, WebGL2 can only render to the main canvas",
+                secondary.name
+            );
+            continue;
+        }
+
+        let window = commands
+            .spawn((
+                Window {
+                    title: secondary.name.clone(),
+                    resolution: (secondary.canvas.width(), secondary.canvas.height()).into(),
+                    ..default()
+                },
+                CanvasName(secondary.name.clone()),
+            ))
+            .id();
+
+        displays.windows.insert(secondary.name.clone(), window);
+    }
+}
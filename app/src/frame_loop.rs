@@ -0,0 +1,183 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use js_sys::{Promise, Reflect};
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::DedicatedWorkerGlobalScope;
+
+/// How early a frame may arrive, as a fraction of the target interval, and still be run.
+/// Absorbs the jitter in `requestAnimationFrame` timestamps.
+const JITTER_TOLERANCE: f64 = 0.1;
+
+/// Decides which display frames to run the app on, so it never exceeds a target frame rate,
+/// and notices when frames were missed.
+#[derive(Clone, Debug)]
+pub struct FramePacer {
+    interval_ms: f64,
+    last_frame: Option<f64>,
+    /// When the next frame is due. Advanced by whole intervals rather than set from the time
+    /// frames actually run, so display frames that don't line up with the target don't add up
+    /// to a lower frame rate.
+    next_due: f64,
+}
+
+/// Timing of a frame that [`FramePacer::tick`] decided to run.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FrameTiming {
+    /// Time since the previous frame that was run.
+    pub delta: Duration,
+    /// Number of target intervals that passed without a frame being run.
+    pub skipped: u32,
+}
+
+impl FramePacer {
+    /// # Panics
+    ///
+    /// If `target_fps` isn't a finite number above zero.
+    pub fn new(target_fps: f64) -> Self {
+        assert!(
+            target_fps.is_finite() && target_fps > 0.0,
+            "target frame rate must be finite and above zero, got {target_fps}"
+        );
+
+        Self {
+            interval_ms: 1000.0 / target_fps,
+            last_frame: None,
+            next_due: 0.0,
+        }
+    }
+
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs_f64(self.interval_ms / 1000.0)
+    }
+
+    /// Forgets the last frame, so the next one runs immediately without counting skipped frames.
+    pub fn reset(&mut self) {
+        self.last_frame = None;
+    }
+
+    /// How long to wait from `now` until the next frame is due, for when there is no
+    /// `requestAnimationFrame` to wait on.
+    pub fn time_until_next(&self, now: f64) -> Duration {
+        let due = self.last_frame.map_or(now, |_| self.next_due);
+
+        Duration::from_secs_f64((due - now).max(0.0) / 1000.0)
+    }
+
+    /// Called on every display frame with its timestamp in milliseconds. Returns `None` if the
+    /// frame should be skipped to stay under the target frame rate.
+    pub fn tick(&mut self, now: f64) -> Option<FrameTiming> {
+        let Some(last) = self.last_frame else {
+            self.last_frame = Some(now);
+            self.next_due = now + self.interval_ms;
+
+            return Some(FrameTiming {
+                delta: Duration::ZERO,
+                skipped: 0,
+            });
+        };
+
+        let late = (now - self.next_due) / self.interval_ms;
+
+        if late < -JITTER_TOLERANCE {
+            return None;
+        }
+
+        // Whole intervals that went by without a frame. Moving the schedule past them keeps it
+        // ahead of `now` after a stall, instead of running frames back to back to catch up.
+        let skipped = (late + JITTER_TOLERANCE).floor() as u32;
+
+        self.last_frame = Some(now);
+        self.next_due += self.interval_ms * (skipped + 1) as f64;
+
+        Some(FrameTiming {
+            delta: Duration::from_secs_f64((now - last).max(0.0) / 1000.0),
+            skipped,
+        })
+    }
+}
+
+/// Decides when the frame loop sends the host its next heartbeat.
+#[derive(Clone, Debug)]
+pub struct HeartbeatTimer {
+    interval_ms: f64,
+    last_sent: Option<f64>,
+}
+
+impl HeartbeatTimer {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval_ms: interval.as_secs_f64() * 1000.0,
+            last_sent: None,
+        }
+    }
+
+    /// Called on every display frame with its timestamp in milliseconds. Returns whether a
+    /// heartbeat should be sent now.
+    pub fn is_due(&mut self, now: f64) -> bool {
+        if self
+            .last_sent
+            .is_some_and(|last| now - last < self.interval_ms)
+        {
+            return false;
+        }
+
+        self.last_sent = Some(now);
+
+        true
+    }
+}
+
+/// Frame counters kept up to date by the frame loop.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameStats {
+    /// Number of frames run so far.
+    pub frames: u64,
+    /// Total number of frames that were due but missed.
+    pub skipped_frames: u64,
+    /// Time between the last two frames.
+    pub last_frame_time: Duration,
+}
+
+impl FrameStats {
+    pub fn record(&mut self, timing: FrameTiming) {
+        self.frames += 1;
+        self.skipped_frames += timing.skipped as u64;
+        self.last_frame_time = timing.delta;
+    }
+}
+
+/// Waits for the next display frame and returns its timestamp in milliseconds.
+///
+/// Uses the worker's `requestAnimationFrame` when the browser provides one, and otherwise
+/// sleeps for `fallback_wait`.
+pub async fn next_frame(fallback_wait: Duration) -> f64 {
+    let global = js_sys::global().unchecked_into::<DedicatedWorkerGlobalScope>();
+
+    if Reflect::has(&global, &"requestAnimationFrame".into()).unwrap_or(false) {
+        let promise = Promise::new(&mut |resolve, _reject| {
+            if global.request_animation_frame(&resolve).is_err() {
+                let _ = resolve.call0(&JsValue::UNDEFINED);
+            }
+        });
+
+        if let Ok(timestamp) = JsFuture::from(promise).await
+            && let Some(timestamp) = timestamp.as_f64()
+        {
+            return timestamp;
+        }
+    } else {
+        gloo_timers::future::sleep(fallback_wait).await;
+    }
+
+    now()
+}
+
+/// The worker's `performance.now()`, in milliseconds.
+pub fn now() -> f64 {
+    js_sys::global()
+        .unchecked_into::<DedicatedWorkerGlobalScope>()
+        .performance()
+        .map_or(0.0, |performance| performance.now())
+}
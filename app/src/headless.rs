@@ -0,0 +1,139 @@
+use std::time::Duration;
+
+use bevy::{prelude::*, render::RenderApp, time::TimeUpdateStrategy};
+use rcade_plugin_input_classic::state::ControllerState;
+
+use crate::{
+    GamePlugin,
+    camera::PlayerView,
+    context::{self, RenderContextLost},
+    hook::RcadeInitError,
+    input::{Controller, MockController, Player},
+};
+
+/// Runs [`GamePlugin`] on top of [`MinimalPlugins`], without a window, renderer or controller.
+///
+/// Time advances by a fixed step on every frame and input comes from a [`MockController`],
+/// which makes gameplay systems deterministic enough to test natively.
+pub struct HeadlessApp {
+    app: App,
+    controller: MockController,
+    context_lost: bool,
+}
+
+impl HeadlessApp {
+    /// Default length of a frame, matching a 60Hz display.
+    pub const FRAME_TIME: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+    pub fn new() -> Self {
+        Self::with_frame_time(Self::FRAME_TIME)
+    }
+
+    pub fn with_frame_time(frame_time: Duration) -> Self {
+        let mut app = App::new();
+
+        let controller = MockController::new();
+
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .init_asset::<Mesh>()
+            .init_asset::<Image>()
+            .init_asset::<StandardMaterial>()
+            .insert_resource(TimeUpdateStrategy::ManualDuration(frame_time))
+            .insert_non_send_resource(Controller::new(controller.clone()))
+            .add_plugins(GamePlugin);
+
+        app.finish();
+        app.cleanup();
+
+        // Run startup systems, so the scene exists before the first step
+        app.update();
+
+        Self {
+            app,
+            controller,
+            context_lost: false,
+        }
+    }
+
+    /// The mock controller the game reads its input from.
+    pub fn controller(&self) -> &MockController {
+        &self.controller
+    }
+
+    /// Sets the controller state seen by every following frame.
+    pub fn set_controller(&mut self, state: ControllerState) {
+        self.controller.hold(state);
+    }
+
+    /// Advances the game by `frames` frames.
+    pub fn step(&mut self, frames: u32) {
+        for _ in 0..frames {
+            if self.context_lost {
+                self.app.main_mut().update();
+            } else {
+                self.app.update();
+            }
+        }
+    }
+
+    /// Plays back a script of controller states, each held for the given number of frames.
+    pub fn run_script(&mut self, script: &[(u32, ControllerState)]) {
+        for &(frames, state) in script {
+            self.controller.queue(frames, state);
+            self.step(frames);
+        }
+    }
+
+    /// Tells the game the render context was lost, as the browser build does, and stops
+    /// extracting to the render world until [`HeadlessApp::restore_context`].
+    pub fn lose_context(&mut self) {
+        self.context_lost = true;
+
+        self.world_mut().write_message(RenderContextLost);
+    }
+
+    /// Replaces the render world with the one `renderer` built, as the browser build does on a
+    /// restored context. Also gives a headless app its first render world.
+    pub fn restore_context(&mut self, renderer: App) -> Result<(), RcadeInitError> {
+        context::release_render_world(&mut self.app);
+        context::adopt_render_world(&mut self.app, renderer)?;
+
+        self.context_lost = false;
+
+        Ok(())
+    }
+
+    /// The world extracted to, if a renderer was given with [`HeadlessApp::restore_context`].
+    pub fn render_world(&self) -> Option<&World> {
+        self.app.get_sub_app(RenderApp).map(SubApp::world)
+    }
+
+    pub fn world(&self) -> &World {
+        self.app.world()
+    }
+
+    pub fn world_mut(&mut self) -> &mut World {
+        self.app.world_mut()
+    }
+
+    /// Transform of player 1's camera, the one that fills the screen unless it is split.
+    pub fn camera_transform(&mut self) -> Transform {
+        self.player_camera(Player::One).0
+    }
+
+    /// Transform and camera settings of `player`'s camera.
+    pub fn player_camera(&mut self, player: Player) -> (Transform, Camera) {
+        self.world_mut()
+            .query::<(&Transform, &Camera, &PlayerView)>()
+            .iter(self.app.world())
+            .find(|(_, _, view)| view.0 == player)
+            .map(|(transform, camera, _)| (*transform, camera.clone()))
+            .expect("a camera for each player")
+    }
+}
+
+impl Default for HeadlessApp {
+    fn default() -> Self {
+        Self::new()
+    }
+}
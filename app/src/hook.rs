@@ -2,52 +2,344 @@
 //
 // This module contains glue code to interface Bevy's rendering with an OffscreenCanvas
 // in a web worker environment. It sets up the necessary window and display handles
-// for wgpu to render to the OffscreenCanvas using WebGL2.
+// for wgpu to render to the OffscreenCanvas using WebGPU or WebGL2.
 //
 // This is required for the project architecture and should not be modified lightly.
 
-use std::{ptr::NonNull, sync::Arc, thread::ThreadId};
+use std::{
+    cell::{Cell, RefCell},
+    ptr::NonNull,
+    rc::Rc,
+    sync::Arc,
+    thread::ThreadId,
+};
 
 use bevy::{
     app::PluginGroupBuilder,
+    light::DirectionalLightShadowMap,
+    log::LogPlugin,
     prelude::*,
     render::{
         RenderDebugFlags, RenderPlugin,
         renderer::{RenderAdapter, RenderAdapterInfo, RenderInstance, RenderQueue, WgpuWrapper},
         settings::RenderCreation,
     },
-    window::{RawHandleWrapper, WindowResolution, WindowWrapper},
+    window::{PrimaryWindow, RawHandleWrapper, WindowResolution, WindowWrapper},
 };
+use js_sys::{Array, Object, Promise, Reflect};
+use protocol::{Border, ErrorKind, Scaling};
 use raw_window_handle::{HasDisplayHandle, HasWindowHandle};
-use wasm_bindgen::{JsCast, JsValue, prelude::wasm_bindgen};
-use web_sys::{OffscreenCanvas, console};
+use wasm_bindgen::{JsCast, JsValue, prelude::Closure};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    DedicatedWorkerGlobalScope, MessageEvent, MessageEventInit, OffscreenCanvas,
+    OffscreenCanvasRenderingContext2d, WebGl2RenderingContext, WebglLoseContext, console,
+};
+
+use crate::{
+    config::RcadeConfig,
+    display::{CanvasName, SecondaryCanvases},
+};
+
+/// Whether `data` is one of the RCade SDK's `plugin_channel` replies, which the SDK listens for
+/// itself, along with the `MessagePort` transferred with it.
+pub(crate) fn is_plugin_channel(data: &JsValue) -> bool {
+    Reflect::get(data, &"type".into())
+        .ok()
+        .and_then(|kind| kind.as_string())
+        .is_some_and(|kind| kind == "plugin_channel")
+}
+
+/// Holds back messages posted to the worker while the app is starting, so none are missed by
+/// listeners that are only installed later on.
+///
+/// Install it before anything else listens for messages. The `CANVAS` message and the RCade
+/// SDK's `plugin_channel` replies are needed during startup, so they are let through.
+///
+/// Dropping it without [`EarlyMessages::replay`], like when startup fails, stops holding
+/// messages back and discards the ones held so far.
+pub struct EarlyMessages {
+    held: Rc<RefCell<Vec<(JsValue, Array)>>>,
+    on_message: Closure<dyn FnMut(MessageEvent)>,
+}
+
+impl EarlyMessages {
+    pub fn install() -> Result<Self, JsValue> {
+        let held = Rc::new(RefCell::new(Vec::new()));
+
+        let on_message = {
+            let held = held.clone();
+
+            Closure::wrap(Box::new(move |event: MessageEvent| {
+                let data = event.data();
+
+                let needed_now = matches!(
+                    protocol::Message::from_js(&data),
+                    Ok(Some(protocol::Message::Canvas { .. }))
+                ) || is_plugin_channel(&data);
+
+                if !needed_now {
+                    event.stop_immediate_propagation();
+                    held.borrow_mut().push((data, event.ports()));
+                }
+            }) as Box<dyn FnMut(MessageEvent)>)
+        };
+
+        js_sys::global()
+            .unchecked_into::<DedicatedWorkerGlobalScope>()
+            .add_event_listener_with_callback("message", on_message.as_ref().unchecked_ref())?;
+
+        Ok(Self { held, on_message })
+    }
+
+    /// Stops holding messages back, and dispatches the ones held so far again, in the order
+    /// they arrived.
+    pub fn replay(self) -> Result<(), JsValue> {
+        let global = js_sys::global().unchecked_into::<DedicatedWorkerGlobalScope>();
+
+        // The replayed messages mustn't be held back again
+        self.uninstall()?;
+
+        let held = self.held.take();
+
+        if !held.is_empty() {
+            console::debug_1(&format!("Replaying {} early message(s)", held.len()).into());
+        }
+
+        for (data, ports) in held {
+            let init = MessageEventInit::new();
+            init.set_data(&data);
+            init.set_ports(&ports);
+
+            let event = MessageEvent::new_with_event_init_dict("message", &init)?;
+            global.dispatch_event(&event)?;
+        }
+
+        Ok(())
+    }
 
-#[wasm_bindgen]
-extern "C" {
-    // This tells wasm-bindgen to look for a global symbol named RUST_OFFSCREEN_CANVAS
-    // on the 'self' (worker's global) scope.
-    #[wasm_bindgen(js_name = RUST_OFFSCREEN_CANVAS, thread_local_v2)]
-    pub static RUST_OFFSCREEN_CANVAS_RAW: JsValue;
+    fn uninstall(&self) -> Result<(), JsValue> {
+        js_sys::global()
+            .unchecked_into::<DedicatedWorkerGlobalScope>()
+            .remove_event_listener_with_callback(
+                "message",
+                self.on_message.as_ref().unchecked_ref(),
+            )
+    }
 }
 
-// You would then cast it to the correct type when you need it:
-pub fn get_offscreen_canvas() -> Result<OffscreenCanvas, JsValue> {
-    RUST_OFFSCREEN_CANVAS_RAW
-        .with(|v| v.clone())
-        .dyn_into::<OffscreenCanvas>()
-        .map_err(|_| JsValue::from_str("Global RUST_OFFSCREEN_CANVAS not found or wrong type."))
+impl Drop for EarlyMessages {
+    /// The listener would outlive its closure otherwise, and fail on every following message.
+    fn drop(&mut self) {
+        if let Err(e) = self.uninstall() {
+            console::error_2(&"Failed to stop holding back messages:".into(), &e);
+        }
+    }
+}
+
+/// Tells the host the worker is listening with a `READY` message, and waits for the `CANVAS`
+/// message it answers with. Returns the main canvas and any secondary display canvases.
+pub async fn receive_canvas() -> Result<(OffscreenCanvas, SecondaryCanvases), RcadeInitError> {
+    let global = js_sys::global().unchecked_into::<DedicatedWorkerGlobalScope>();
+
+    let mut resolve_canvas = None;
+    let canvas_received = Promise::new(&mut |resolve, _reject| resolve_canvas = Some(resolve));
+    let resolve_canvas = resolve_canvas.expect("Promise::new calls its executor immediately");
+
+    let on_message = Closure::wrap(Box::new(move |event: MessageEvent| {
+        if let Ok(Some(protocol::Message::Canvas { .. })) =
+            protocol::Message::from_js(&event.data())
+        {
+            let _ = resolve_canvas.call1(&JsValue::UNDEFINED, &event.data());
+        }
+    }) as Box<dyn FnMut(MessageEvent)>);
+
+    global
+        .add_event_listener_with_callback("message", on_message.as_ref().unchecked_ref())
+        .and_then(|_| post_to_host(&protocol::Message::Ready))
+        .map_err(RcadeInitError::host)?;
+
+    let canvas = JsFuture::from(canvas_received).await;
+
+    global
+        .remove_event_listener_with_callback("message", on_message.as_ref().unchecked_ref())
+        .map_err(RcadeInitError::host)?;
+
+    match protocol::Message::from_js(&canvas.map_err(RcadeInitError::host)?) {
+        Ok(Some(protocol::Message::Canvas { canvas, displays })) => {
+            Ok((canvas, SecondaryCanvases(displays)))
+        }
+        _ => Err(RcadeInitError::MissingCanvas),
+    }
+}
+
+/// Everything that can go wrong while setting up rendering on the OffscreenCanvas.
+#[derive(Debug)]
+pub enum RcadeInitError {
+    /// The worker never received an OffscreenCanvas from the host.
+    MissingCanvas,
+    /// Talking to the host failed before the canvas arrived.
+    Host(String),
+    /// The WebGL2 context couldn't be created with the configured attributes.
+    Context(String),
+    /// The canvas could not be turned into a wgpu surface target.
+    SurfaceTarget(raw_window_handle::HandleError),
+    /// wgpu could not create a surface, usually because WebGL2 is unavailable.
+    Surface(wgpu::CreateSurfaceError),
+    /// No GPU adapter is compatible with the surface.
+    Adapter(wgpu::RequestAdapterError),
+    /// The adapter refused to create a device with the limits we need.
+    Device(wgpu::RequestDeviceError),
+    /// WebGPU was requested but the browser doesn't expose it to workers.
+    WebGpuUnsupported,
+    /// Rendering wasn't set up on the device that replaced a lost one.
+    MissingRenderWorld,
+}
+
+impl std::fmt::Display for RcadeInitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RcadeInitError::MissingCanvas => {
+                write!(f, "The host didn't send an OffscreenCanvas")
+            }
+            RcadeInitError::Host(e) => write!(f, "Failed to talk to the host: {e}"),
+            RcadeInitError::Context(e) => write!(f, "Failed to create WebGL2 context: {e}"),
+            RcadeInitError::SurfaceTarget(e) => write!(f, "Failed to create surface target: {e}"),
+            RcadeInitError::Surface(e) => write!(f, "Failed to create surface: {e}"),
+            RcadeInitError::Adapter(e) => write!(f, "Failed to find suitable GPU adapter: {e}"),
+            RcadeInitError::Device(e) => write!(f, "Failed to create device: {e}"),
+            RcadeInitError::WebGpuUnsupported => {
+                write!(f, "WebGPU is not supported in this browser's workers")
+            }
+            RcadeInitError::MissingRenderWorld => {
+                write!(f, "No render world was built on the new device")
+            }
+        }
+    }
+}
+
+impl RcadeInitError {
+    pub(crate) fn host(e: JsValue) -> Self {
+        RcadeInitError::Host(format!("{e:?}"))
+    }
+
+    /// The `ERROR` message reporting this to the host.
+    pub fn to_message(&self) -> protocol::Message {
+        protocol::Message::Error {
+            kind: ErrorKind::Init,
+            message: self.to_string(),
+            location: None,
+            backtrace: None,
+        }
+    }
+}
+
+impl std::error::Error for RcadeInitError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RcadeInitError::MissingCanvas
+            | RcadeInitError::Host(_)
+            | RcadeInitError::Context(_)
+            | RcadeInitError::WebGpuUnsupported
+            | RcadeInitError::MissingRenderWorld => None,
+            RcadeInitError::SurfaceTarget(e) => Some(e),
+            RcadeInitError::Surface(e) => Some(e),
+            RcadeInitError::Adapter(e) => Some(e),
+            RcadeInitError::Device(e) => Some(e),
+        }
+    }
+}
+
+/// Posts `message` to the host page, transferring anything that can't be copied.
+pub fn post_to_host(message: &protocol::Message) -> Result<(), JsValue> {
+    js_sys::global()
+        .unchecked_into::<DedicatedWorkerGlobalScope>()
+        .post_message_with_transfer(&message.to_js()?, &message.transferables())
+}
+
+/// Draws a diagnostic for `error` on the canvas, if there is one, and posts an `ERROR` message
+/// to the host, which shows it over the canvas.
+///
+/// WebGL2 is checked for on a throwaway canvas before a context is created on the real one, so
+/// the canvas can be drawn on after most errors. The message to the host is what
+/// guarantees the error is seen when it can't.
+pub fn report_init_error(error: &RcadeInitError, canvas: Option<&OffscreenCanvas>) {
+    console::error_1(&format!("Failed to initialize renderer: {error}").into());
+
+    if let Some(canvas) = canvas
+        && let Err(e) = draw_error_screen(canvas, "Unable to start the game", &error.to_string())
+    {
+        console::warn_2(&"Could not draw error screen:".into(), &e);
+    }
+
+    if let Err(e) = post_to_host(&error.to_message()) {
+        console::error_2(&"Failed to report error to host:".into(), &e);
+    }
+}
+
+fn draw_error_screen(canvas: &OffscreenCanvas, title: &str, detail: &str) -> Result<(), JsValue> {
+    let context = canvas
+        .get_context("2d")?
+        .ok_or_else(|| JsValue::from_str("2D context unavailable"))?
+        .dyn_into::<OffscreenCanvasRenderingContext2d>()?;
+
+    let width = canvas.width() as f64;
+    let height = canvas.height() as f64;
+    let font_size = (height / 20.0).max(10.0);
+    let margin = font_size;
+
+    context.set_fill_style_str("#200000");
+    context.fill_rect(0.0, 0.0, width, height);
+
+    context.set_fill_style_str("#ff6666");
+    context.set_font(&format!("bold {font_size}px monospace"));
+    context.set_text_baseline("top");
+    context.fill_text(title, margin, margin)?;
+
+    context.set_fill_style_str("#ffffff");
+    context.set_font(&format!("{}px monospace", font_size * 0.75));
+
+    // Canvas text doesn't wrap, so break the detail into lines that fit by hand
+    let mut y = margin + font_size * 2.0;
+    let mut line = String::new();
+
+    for word in detail.split_whitespace() {
+        let candidate = if line.is_empty() {
+            word.to_string()
+        } else {
+            format!("{line} {word}")
+        };
+
+        if !line.is_empty() && context.measure_text(&candidate)?.width() > width - margin * 2.0 {
+            context.fill_text(&line, margin, y)?;
+            y += font_size;
+            line = word.to_string();
+        } else {
+            line = candidate;
+        }
+    }
+
+    if !line.is_empty() {
+        context.fill_text(&line, margin, y)?;
+    }
+
+    Ok(())
 }
 
 pub(crate) struct OffscreenWindowHandle {
     window_handle: raw_window_handle::RawWindowHandle,
     display_handle: raw_window_handle::DisplayHandle<'static>,
     thread_id: ThreadId,
+    /// The raw handle points at this, so it is boxed to stay put for as long as the handle lives.
+    _canvas: Box<OffscreenCanvas>,
 }
 
 impl OffscreenWindowHandle {
     pub(crate) fn new(canvas: &OffscreenCanvas) -> Self {
+        let canvas = Box::new(canvas.clone());
+
         // Equivalent to WebOffscreenCanvasWindowHandle::from_wasm_bindgen_0_2
-        let ptr = NonNull::from(canvas).cast();
+        let ptr = NonNull::from(&*canvas).cast();
         let handle = raw_window_handle::WebOffscreenCanvasWindowHandle::new(ptr);
         let window_handle = raw_window_handle::RawWindowHandle::WebOffscreenCanvas(handle);
         let display_handle = raw_window_handle::DisplayHandle::web();
@@ -56,6 +348,7 @@ impl OffscreenWindowHandle {
             window_handle,
             display_handle,
             thread_id: std::thread::current().id(),
+            _canvas: canvas,
         }
     }
 }
@@ -90,26 +383,362 @@ impl HasDisplayHandle for OffscreenWindowHandle {
     }
 }
 
+type NewWindow<'a> = (Entity, Has<PrimaryWindow>, Option<&'a CanvasName>);
+
+/// Connects each new window to the canvas it renders to: the main canvas for the primary window,
+/// and the secondary display canvas named by its [`CanvasName`] for any other.
 pub fn setup_added_window(
     mut commands: Commands,
-    canvas: NonSendMut<OffscreenCanvas>,
-    mut new_windows: Query<Entity, Added<Window>>,
+    canvas: NonSend<OffscreenCanvas>,
+    secondary: Option<NonSend<SecondaryCanvases>>,
+    new_windows: Query<NewWindow, Added<Window>>,
 ) {
-    // This system should only be called once at startup and there should only
-    // be one window that's been added.
-    let Some(entity) = new_windows.iter_mut().next() else {
-        panic!("Multiple windows added")
+    for (entity, is_primary, name) in &new_windows {
+        let window_canvas = if is_primary {
+            Some(&*canvas)
+        } else {
+            name.zip(secondary.as_deref())
+                .and_then(|(name, secondary)| secondary.get(&name.0))
+                .map(|display| &display.canvas)
+        };
+
+        let Some(window_canvas) = window_canvas else {
+            warn!(
+                "Window {entity} has no canvas to render to, it needs the `CanvasName` of one of \
+                 the host's displays"
+            );
+            continue;
+        };
+
+        let handle = OffscreenWindowHandle::new(window_canvas);
+
+        let handle = RawHandleWrapper::new(&WindowWrapper::new(handle))
+            .expect("to create offscreen raw handle wrapper. If this fails, multiple threads are trying to access the same canvas!");
+
+        commands.entity(entity).insert(handle);
+    }
+}
+
+/// How the render resolution follows the size of the display the canvas is shown on.
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub enum ResolutionPolicy {
+    /// Render at a fixed internal resolution and let the host scale the canvas.
+    Fixed { width: u32, height: u32 },
+    /// Render at the physical resolution the canvas is displayed at.
+    MatchDisplay,
+}
+
+/// The RCade cabinet's native resolution.
+pub const DEFAULT_RESOLUTION: (u32, u32) = (336, 262);
+
+impl Default for ResolutionPolicy {
+    fn default() -> Self {
+        ResolutionPolicy::Fixed {
+            width: DEFAULT_RESOLUTION.0,
+            height: DEFAULT_RESOLUTION.1,
+        }
+    }
+}
+
+/// Display size reported by the host in a `RESIZE` message, in physical pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DisplaySize {
+    pub width: u32,
+    pub height: u32,
+    pub scale_factor: f32,
+}
+
+impl DisplaySize {
+    fn from_message(message: &protocol::Message) -> Option<Self> {
+        match *message {
+            protocol::Message::Resize {
+                width,
+                height,
+                scale_factor,
+            } => Some(Self {
+                width,
+                height,
+                scale_factor: scale_factor as f32,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Listens for `RESIZE` messages posted to the worker and remembers the latest one.
+pub struct DisplaySizeListener {
+    latest: Rc<Cell<Option<DisplaySize>>>,
+    on_message: Closure<dyn FnMut(MessageEvent)>,
+}
+
+impl DisplaySizeListener {
+    pub fn install() -> Result<Self, JsValue> {
+        let latest = Rc::new(Cell::new(None));
+
+        let on_message = {
+            let latest = latest.clone();
+
+            Closure::wrap(Box::new(move |event: MessageEvent| {
+                match protocol::Message::from_js(&event.data()) {
+                    Ok(Some(message)) => {
+                        if let Some(size) = DisplaySize::from_message(&message) {
+                            latest.set(Some(size));
+                        }
+                    }
+                    Ok(None) => {}
+                    // Every listener sees every message, so only this one reports bad ones
+                    Err(e) => console::warn_2(
+                        &format!("Rejected message from host: {e}").into(),
+                        &event.data(),
+                    ),
+                }
+            }) as Box<dyn FnMut(MessageEvent)>)
+        };
+
+        js_sys::global()
+            .unchecked_into::<DedicatedWorkerGlobalScope>()
+            .add_event_listener_with_callback("message", on_message.as_ref().unchecked_ref())?;
+
+        Ok(Self { latest, on_message })
+    }
+
+    pub fn latest(&self) -> Option<DisplaySize> {
+        self.latest.get()
+    }
+}
+
+impl Drop for DisplaySizeListener {
+    fn drop(&mut self) {
+        if let Err(e) = js_sys::global()
+            .unchecked_into::<DedicatedWorkerGlobalScope>()
+            .remove_event_listener_with_callback(
+                "message",
+                self.on_message.as_ref().unchecked_ref(),
+            )
+        {
+            console::error_2(&"Failed to stop listening for display sizes:".into(), &e);
+        }
+    }
+}
+
+/// Keeps the primary window and the OffscreenCanvas backing store in sync with the
+/// [`ResolutionPolicy`] and the display size last reported by the host.
+pub fn apply_display_size(
+    policy: Res<ResolutionPolicy>,
+    listener: NonSend<DisplaySizeListener>,
+    canvas: NonSend<OffscreenCanvas>,
+    mut applied: Local<Option<DisplaySize>>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    let display = listener.latest();
+
+    if !policy.is_changed() && display == *applied {
+        return;
+    }
+
+    *applied = display;
+
+    let Ok(mut window) = windows.single_mut() else {
+        return;
+    };
+
+    let (width, height) = match *policy {
+        ResolutionPolicy::Fixed { width, height } => (width, height),
+        ResolutionPolicy::MatchDisplay => match display {
+            Some(size) => {
+                window.resolution.set_scale_factor(size.scale_factor);
+                (size.width, size.height)
+            }
+            // The host sizes the backing store to the display before handing it over
+            None => (canvas.width(), canvas.height()),
+        },
     };
 
-    let handle = OffscreenWindowHandle::new(&canvas);
+    if window.resolution.physical_size() != UVec2::new(width, height) {
+        window.resolution.set_physical_resolution(width, height);
+    }
+
+    if canvas.width() != width || canvas.height() != height {
+        canvas.set_width(width);
+        canvas.set_height(height);
+    }
+}
+
+/// How the host fits the canvas into the page. Sent to it in the `INIT` message at startup.
+#[derive(Resource, Clone, Debug, Default, PartialEq)]
+pub enum HostScaling {
+    /// Stretch the canvas over the whole page.
+    #[default]
+    Stretch,
+    /// Keep the aspect ratio of a [`ResolutionPolicy::Fixed`] resolution, upscaling it by whole
+    /// numbers with nearest-neighbour filtering, and fill the rest of the page with `border`.
+    IntegerScale { border: LetterboxBorder },
+}
 
-    let handle = RawHandleWrapper::new(&WindowWrapper::new(handle))
-        .expect("to create offscreen raw handle wrapper. If this fails, multiple threads are trying to access the same canvas!");
+/// What fills the letterbox around an integer-scaled canvas.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LetterboxBorder {
+    Color(Color),
+    /// URL of an image, relative to the host page.
+    Image(String),
+}
+
+impl Default for LetterboxBorder {
+    fn default() -> Self {
+        LetterboxBorder::Color(Color::BLACK)
+    }
+}
 
-    commands.entity(entity).insert(handle);
+/// Sends the `INIT` message telling the host how to lay out the canvas.
+pub fn announce_display(policy: Res<ResolutionPolicy>, scaling: Res<HostScaling>) {
+    let scaling = match (&*scaling, *policy) {
+        (HostScaling::IntegerScale { border }, ResolutionPolicy::Fixed { width, height }) => {
+            Scaling::Integer {
+                width,
+                height,
+                border: match border {
+                    LetterboxBorder::Color(color) => Border::Color(color.to_srgba().to_hex()),
+                    LetterboxBorder::Image(url) => Border::Image(url.clone()),
+                },
+            }
+        }
+        (HostScaling::IntegerScale { .. }, ResolutionPolicy::MatchDisplay) => {
+            warn!("Integer scaling needs a fixed resolution, falling back to stretching");
+            Scaling::Stretch
+        }
+        (HostScaling::Stretch, _) => Scaling::Stretch,
+    };
+
+    if let Err(e) = post_to_host(&protocol::Message::Init { scaling }) {
+        console::error_2(&"Failed to send INIT message to host:".into(), &e);
+    }
+}
+
+/// Which graphics backend [`RcadePluginExt`] should render with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BackendPreference {
+    /// Use WebGPU when the browser supports it in workers, and WebGL2 otherwise.
+    #[default]
+    Auto,
+    /// Only try WebGPU.
+    WebGpu,
+    /// Only try WebGL2.
+    WebGl2,
+}
+
+/// The graphics backend that was chosen when rendering was initialized.
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RcadeBackend {
+    WebGpu,
+    WebGl2,
+}
+
+async fn initialize_renderer(
+    canvas: &web_sys::OffscreenCanvas,
+    config: &RcadeConfig,
+) -> Result<(RenderResources, RcadeBackend), RcadeInitError> {
+    match config.backend {
+        BackendPreference::WebGpu => Ok((
+            initialize_webgpu(canvas, config).await?,
+            RcadeBackend::WebGpu,
+        )),
+        BackendPreference::WebGl2 => Ok((
+            initialize_webgl2(canvas, config).await?,
+            RcadeBackend::WebGl2,
+        )),
+        BackendPreference::Auto => match initialize_webgpu(canvas, config).await {
+            Ok(render_resources) => Ok((render_resources, RcadeBackend::WebGpu)),
+            Err(e) => {
+                console::warn_1(
+                    &format!("WebGPU unavailable ({e}), falling back to WebGL2").into(),
+                );
+
+                Ok((
+                    initialize_webgl2(canvas, config).await?,
+                    RcadeBackend::WebGl2,
+                ))
+            }
+        },
+    }
 }
 
-async fn initialize_webgl2(canvas: &web_sys::OffscreenCanvas) -> Result<RenderResources, String> {
+async fn initialize_webgpu(
+    canvas: &web_sys::OffscreenCanvas,
+    config: &RcadeConfig,
+) -> Result<RenderResources, RcadeInitError> {
+    console::log_1(&"Initializing WebGPU manually...".into());
+
+    if !wgpu::util::is_browser_webgpu_supported().await {
+        return Err(RcadeInitError::WebGpuUnsupported);
+    }
+
+    let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+        backends: wgpu::Backends::BROWSER_WEBGPU,
+        flags: wgpu::InstanceFlags::default(),
+        ..Default::default()
+    });
+
+    console::log_1(&"Created wgpu instance".into());
+
+    // Get as far as a device before touching the canvas: once a "webgpu" context has been
+    // created on it, falling back to WebGL2 on the same canvas is no longer possible.
+    let adapter = instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: config.power_preference,
+            compatible_surface: None,
+            force_fallback_adapter: false,
+        })
+        .await
+        .map_err(RcadeInitError::Adapter)?;
+
+    console::log_1(&format!("Found adapter: {:?}", adapter.get_info()).into());
+
+    // WebGPU guarantees at least the downlevel limits, including storage buffers and compute
+    let (device, queue) = adapter
+        .request_device(&wgpu::DeviceDescriptor {
+            label: Some("bevy_device"),
+            required_features: wgpu::Features::empty(),
+            required_limits: config
+                .required_limits
+                .clone()
+                .unwrap_or_else(wgpu::Limits::downlevel_defaults)
+                .using_resolution(adapter.limits()),
+            ..Default::default()
+        })
+        .await
+        .map_err(RcadeInitError::Device)?;
+
+    console::log_1(&"Created device and queue".into());
+
+    // Bevy creates its own surface later on, this one only checks that the canvas can actually
+    // host a WebGPU context. It is the last step, so if it fails no context was created and the
+    // canvas is still free for WebGL2.
+    let window_handle = OffscreenWindowHandle::new(canvas);
+
+    let surface_target = unsafe { wgpu::SurfaceTargetUnsafe::from_window(&window_handle) }
+        .map_err(RcadeInitError::SurfaceTarget)?;
+
+    let _surface = unsafe { instance.create_surface_unsafe(surface_target) }
+        .map_err(RcadeInitError::Surface)?;
+
+    console::log_1(&"Created surface from OffscreenCanvas".into());
+
+    Ok(RenderResources {
+        instance,
+        adapter,
+        device,
+        queue,
+    })
+}
+
+async fn initialize_webgl2(
+    canvas: &web_sys::OffscreenCanvas,
+    config: &RcadeConfig,
+) -> Result<RenderResources, RcadeInitError> {
+    // Once the canvas has a "webgl2" context it can't get a 2D one for the error screen, so
+    // check that WebGL2 is there at all on a throwaway canvas first
+    probe_webgl2().map_err(|e| RcadeInitError::Context(format!("{e:?}")))?;
+
     console::log_1(&"Initializing WebGL2 manually...".into());
 
     // Create wgpu instance with GL backend
@@ -118,7 +747,7 @@ async fn initialize_webgl2(canvas: &web_sys::OffscreenCanvas) -> Result<RenderRe
         flags: wgpu::InstanceFlags::default(),
         backend_options: wgpu::BackendOptions {
             gl: wgpu::GlBackendOptions {
-                gles_minor_version: wgpu::Gles3MinorVersion::Version0,
+                gles_minor_version: config.gles_minor_version,
                 ..Default::default()
             },
             ..Default::default()
@@ -128,26 +757,28 @@ async fn initialize_webgl2(canvas: &web_sys::OffscreenCanvas) -> Result<RenderRe
 
     console::log_1(&"Created wgpu instance".into());
 
+    create_webgl2_context(canvas, config).map_err(|e| RcadeInitError::Context(format!("{e:?}")))?;
+
     // Create the window handle and surface
     let window_handle = OffscreenWindowHandle::new(canvas);
 
     let surface_target = unsafe { wgpu::SurfaceTargetUnsafe::from_window(&window_handle) }
-        .map_err(|e| format!("Failed to create surface target: {:?}", e))?;
+        .map_err(RcadeInitError::SurfaceTarget)?;
 
     let surface = unsafe { instance.create_surface_unsafe(surface_target) }
-        .map_err(|e| format!("Failed to create surface: {:?}", e))?;
+        .map_err(RcadeInitError::Surface)?;
 
     console::log_1(&"Created surface from OffscreenCanvas".into());
 
     // Request adapter with the compatible surface
     let adapter = instance
         .request_adapter(&wgpu::RequestAdapterOptions {
-            power_preference: wgpu::PowerPreference::HighPerformance,
+            power_preference: config.power_preference,
             compatible_surface: Some(&surface),
             force_fallback_adapter: false,
         })
         .await
-        .map_err(|e| format!("Failed to find suitable GPU adapter: {e:?}"))?;
+        .map_err(RcadeInitError::Adapter)?;
 
     console::log_1(&format!("Found adapter: {:?}", adapter.get_info()).into());
 
@@ -156,12 +787,15 @@ async fn initialize_webgl2(canvas: &web_sys::OffscreenCanvas) -> Result<RenderRe
         .request_device(&wgpu::DeviceDescriptor {
             label: Some("bevy_device"),
             required_features: wgpu::Features::empty(),
-            required_limits: wgpu::Limits::downlevel_webgl2_defaults()
+            required_limits: config
+                .required_limits
+                .clone()
+                .unwrap_or_else(wgpu::Limits::downlevel_webgl2_defaults)
                 .using_resolution(adapter.limits()),
             ..Default::default()
         })
         .await
-        .map_err(|e| format!("Failed to create device: {:?}", e))?;
+        .map_err(RcadeInitError::Device)?;
 
     console::log_1(&"Created device and queue".into());
 
@@ -173,6 +807,86 @@ async fn initialize_webgl2(canvas: &web_sys::OffscreenCanvas) -> Result<RenderRe
     })
 }
 
+/// Checks that a WebGL2 context can be created, without touching the real canvas.
+fn probe_webgl2() -> Result<(), JsValue> {
+    let context = OffscreenCanvas::new(1, 1)?
+        .get_context("webgl2")?
+        .ok_or_else(|| JsValue::from_str("WebGL2 is unavailable"))?;
+
+    // Browsers only allow a handful of contexts per page, so give this one back straight away
+    // rather than when it is garbage collected
+    if let Some(lose_context) = context
+        .unchecked_into::<WebGl2RenderingContext>()
+        .get_extension("WEBGL_lose_context")?
+    {
+        lose_context
+            .unchecked_into::<WebglLoseContext>()
+            .lose_context();
+    }
+
+    Ok(())
+}
+
+/// Attributes the WebGL2 context is created with.
+///
+/// The defaults are the ones the context has always had: WebGL's own, apart from
+/// `desynchronized`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ContextAttributes {
+    /// Let the browser skip compositor synchronization, trading tearing for latency.
+    pub desynchronized: bool,
+    pub antialias: bool,
+    /// Give the canvas an alpha channel, so the page shows through transparent pixels.
+    pub alpha: bool,
+    /// The context's `powerPreference`. `None` leaves it to the browser.
+    pub power_preference: Option<wgpu::PowerPreference>,
+}
+
+impl Default for ContextAttributes {
+    fn default() -> Self {
+        Self {
+            desynchronized: true,
+            antialias: false,
+            alpha: true,
+            power_preference: None,
+        }
+    }
+}
+
+/// Creates the canvas's WebGL2 context with the configured attributes.
+///
+/// wgpu asks for the context with its own attributes, but a canvas only ever has one context
+/// per type, so creating it first is what lets our attributes win.
+fn create_webgl2_context(canvas: &OffscreenCanvas, config: &RcadeConfig) -> Result<(), JsValue> {
+    let attributes = Object::new();
+    let set = |key: &str, value: &JsValue| Reflect::set(&attributes, &key.into(), value);
+
+    set(
+        "desynchronized",
+        &config.context_attributes.desynchronized.into(),
+    )?;
+    set("antialias", &config.context_attributes.antialias.into())?;
+    set("alpha", &config.context_attributes.alpha.into())?;
+
+    if let Some(power_preference) = config.context_attributes.power_preference {
+        let power_preference = match power_preference {
+            wgpu::PowerPreference::None => "default",
+            wgpu::PowerPreference::LowPower => "low-power",
+            wgpu::PowerPreference::HighPerformance => "high-performance",
+        };
+
+        set("powerPreference", &power_preference.into())?;
+    }
+
+    canvas
+        .get_context_with_context_options("webgl2", &attributes)?
+        .ok_or_else(|| JsValue::from_str("WebGL2 is unavailable"))?;
+
+    console::debug_2(&"Created WebGL2 context with".into(), &attributes);
+
+    Ok(())
+}
+
 struct RenderResources {
     instance: wgpu::Instance,
     adapter: wgpu::Adapter,
@@ -180,37 +894,87 @@ struct RenderResources {
     queue: wgpu::Queue,
 }
 
+/// Inserts the resources derived from [`RcadeConfig`], along with the chosen [`RcadeBackend`].
+struct RcadeConfigPlugin {
+    config: RcadeConfig,
+    backend: RcadeBackend,
+}
+
+impl Plugin for RcadeConfigPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.config.resolution)
+            .insert_resource(self.config.scaling.clone())
+            .insert_resource(DirectionalLightShadowMap {
+                size: self.config.shadow_map_size,
+            })
+            .insert_resource(self.backend)
+            .insert_resource(self.config.clone());
+    }
+}
+
 pub trait RcadePluginExt {
-    fn with_rcade(self, canvas: OffscreenCanvas) -> impl Future<Output = PluginGroupBuilder>
+    /// Sets up rendering to `canvas` with the default [`RcadeConfig`].
+    fn with_rcade(
+        self,
+        canvas: OffscreenCanvas,
+    ) -> impl Future<Output = Result<PluginGroupBuilder, RcadeInitError>>
+    where
+        Self: Sized;
+
+    /// Sets up rendering to `canvas` with the given `config`.
+    fn with_rcade_config(
+        self,
+        canvas: OffscreenCanvas,
+        config: RcadeConfig,
+    ) -> impl Future<Output = Result<PluginGroupBuilder, RcadeInitError>>
     where
         Self: Sized;
 }
 
 impl RcadePluginExt for DefaultPlugins {
-    async fn with_rcade(self, canvas: OffscreenCanvas) -> PluginGroupBuilder {
-        // Manually initialize WebGL2 rendering resources
-        let render_resources = initialize_webgl2(&canvas)
-            .await
-            .expect("Failed to initialize WebGL2 renderer");
-
-        self.set(bevy::window::WindowPlugin {
-            primary_window: Some(Window {
-                resolution: WindowResolution::new(336, 262),
+    async fn with_rcade(
+        self,
+        canvas: OffscreenCanvas,
+    ) -> Result<PluginGroupBuilder, RcadeInitError> {
+        self.with_rcade_config(canvas, RcadeConfig::default()).await
+    }
+
+    async fn with_rcade_config(
+        self,
+        canvas: OffscreenCanvas,
+        config: RcadeConfig,
+    ) -> Result<PluginGroupBuilder, RcadeInitError> {
+        // Manually initialize WebGPU or WebGL2 rendering resources
+        let (render_resources, backend) = initialize_renderer(&canvas, &config).await?;
+
+        console::log_1(&format!("Rendering with {backend:?}").into());
+
+        let (width, height) = config.initial_resolution();
+
+        Ok(self
+            .set(bevy::window::WindowPlugin {
+                primary_window: Some(Window {
+                    resolution: WindowResolution::new(width, height),
+                    ..Default::default()
+                }),
+                exit_condition: bevy::window::ExitCondition::DontExit,
                 ..Default::default()
-            }),
-            exit_condition: bevy::window::ExitCondition::DontExit,
-            ..Default::default()
-        })
-        .set(RenderPlugin {
-            debug_flags: RenderDebugFlags::default(),
-            render_creation: RenderCreation::Manual(bevy::render::settings::RenderResources(
-                render_resources.device.into(),
-                RenderQueue(Arc::new(WgpuWrapper::new(render_resources.queue))),
-                RenderAdapterInfo(WgpuWrapper::new(render_resources.adapter.get_info())),
-                RenderAdapter(Arc::new(WgpuWrapper::new(render_resources.adapter))),
-                RenderInstance(Arc::new(WgpuWrapper::new(render_resources.instance))),
-            )),
-            synchronous_pipeline_compilation: false,
-        })
+            })
+            .set(LogPlugin {
+                level: config.log_level,
+                ..Default::default()
+            })
+            .set(RenderPlugin {
+                debug_flags: RenderDebugFlags::default(),
+                render_creation: RenderCreation::Manual(bevy::render::settings::RenderResources(
+                    render_resources.device.into(),
+                    RenderQueue(Arc::new(WgpuWrapper::new(render_resources.queue))),
+                    RenderAdapterInfo(WgpuWrapper::new(render_resources.adapter.get_info())),
+                    RenderAdapter(Arc::new(WgpuWrapper::new(render_resources.adapter))),
+                    RenderInstance(Arc::new(WgpuWrapper::new(render_resources.instance))),
+                )),
+                synchronous_pipeline_compilation: false,
+            })
+            .add(RcadeConfigPlugin { config, backend }))
     }
 }
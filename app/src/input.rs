@@ -0,0 +1,442 @@
+use std::{cell::RefCell, collections::VecDeque, rc::Rc, str::FromStr, time::Duration};
+
+use bevy::{prelude::*, tasks::futures_lite::future};
+use rcade_plugin_input_classic::{ClassicController, state::ControllerState};
+use serde::{Deserialize, Serialize};
+
+/// Samples the controller once per frame in `PreUpdate` and exposes it as a
+/// `ButtonInput<RcadeButton>` resource, which is what game systems should read.
+///
+/// Input comes from the [`Controller`] non-send resource when there is one. Other sources, like
+/// [`sample_keyboard`], write [`ControllerInput`] in the [`ControllerInputSystems`] set instead.
+pub struct RcadeInputPlugin;
+
+impl Plugin for RcadeInputPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ControllerInput>()
+            .init_resource::<ButtonInput<RcadeButton>>()
+            .add_systems(
+                PreUpdate,
+                (
+                    sample_controller.in_set(ControllerInputSystems),
+                    update_buttons.after(ControllerInputSystems),
+                ),
+            )
+            .add_systems(Update, update_controller_notice);
+    }
+}
+
+/// Systems that write [`ControllerInput`] for the current frame.
+#[derive(SystemSet, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ControllerInputSystems;
+
+/// One of the two players on the cabinet.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Player {
+    One,
+    Two,
+}
+
+/// A button that each player has their own copy of.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PlayerButton {
+    Up,
+    Down,
+    Left,
+    Right,
+    A,
+    B,
+}
+
+/// A button on the classic controller, used as the key of `ButtonInput<RcadeButton>`.
+///
+/// Serialized by [`RcadeButton::name`], for use in data files like the action bindings.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum RcadeButton {
+    Player(Player, PlayerButton),
+    /// The shared one player start button.
+    OnePlayer,
+    /// The shared two player start button.
+    TwoPlayer,
+}
+
+impl RcadeButton {
+    pub const ALL: [RcadeButton; 14] = [
+        RcadeButton::Player(Player::One, PlayerButton::Up),
+        RcadeButton::Player(Player::One, PlayerButton::Down),
+        RcadeButton::Player(Player::One, PlayerButton::Left),
+        RcadeButton::Player(Player::One, PlayerButton::Right),
+        RcadeButton::Player(Player::One, PlayerButton::A),
+        RcadeButton::Player(Player::One, PlayerButton::B),
+        RcadeButton::Player(Player::Two, PlayerButton::Up),
+        RcadeButton::Player(Player::Two, PlayerButton::Down),
+        RcadeButton::Player(Player::Two, PlayerButton::Left),
+        RcadeButton::Player(Player::Two, PlayerButton::Right),
+        RcadeButton::Player(Player::Two, PlayerButton::A),
+        RcadeButton::Player(Player::Two, PlayerButton::B),
+        RcadeButton::OnePlayer,
+        RcadeButton::TwoPlayer,
+    ];
+
+    /// Name of the button in data files, matching the [`ControllerState`] field it reads.
+    pub fn name(self) -> &'static str {
+        match self {
+            RcadeButton::Player(Player::One, PlayerButton::Up) => "player1_up",
+            RcadeButton::Player(Player::One, PlayerButton::Down) => "player1_down",
+            RcadeButton::Player(Player::One, PlayerButton::Left) => "player1_left",
+            RcadeButton::Player(Player::One, PlayerButton::Right) => "player1_right",
+            RcadeButton::Player(Player::One, PlayerButton::A) => "player1_a",
+            RcadeButton::Player(Player::One, PlayerButton::B) => "player1_b",
+            RcadeButton::Player(Player::Two, PlayerButton::Up) => "player2_up",
+            RcadeButton::Player(Player::Two, PlayerButton::Down) => "player2_down",
+            RcadeButton::Player(Player::Two, PlayerButton::Left) => "player2_left",
+            RcadeButton::Player(Player::Two, PlayerButton::Right) => "player2_right",
+            RcadeButton::Player(Player::Two, PlayerButton::A) => "player2_a",
+            RcadeButton::Player(Player::Two, PlayerButton::B) => "player2_b",
+            RcadeButton::OnePlayer => "one_player",
+            RcadeButton::TwoPlayer => "two_player",
+        }
+    }
+
+    /// Whether this button is held down in `state`.
+    pub fn is_held(self, state: &ControllerState) -> bool {
+        match self {
+            RcadeButton::Player(Player::One, PlayerButton::Up) => state.player1_up,
+            RcadeButton::Player(Player::One, PlayerButton::Down) => state.player1_down,
+            RcadeButton::Player(Player::One, PlayerButton::Left) => state.player1_left,
+            RcadeButton::Player(Player::One, PlayerButton::Right) => state.player1_right,
+            RcadeButton::Player(Player::One, PlayerButton::A) => state.player1_a,
+            RcadeButton::Player(Player::One, PlayerButton::B) => state.player1_b,
+            RcadeButton::Player(Player::Two, PlayerButton::Up) => state.player2_up,
+            RcadeButton::Player(Player::Two, PlayerButton::Down) => state.player2_down,
+            RcadeButton::Player(Player::Two, PlayerButton::Left) => state.player2_left,
+            RcadeButton::Player(Player::Two, PlayerButton::Right) => state.player2_right,
+            RcadeButton::Player(Player::Two, PlayerButton::A) => state.player2_a,
+            RcadeButton::Player(Player::Two, PlayerButton::B) => state.player2_b,
+            RcadeButton::OnePlayer => state.system_one_player,
+            RcadeButton::TwoPlayer => state.system_two_player,
+        }
+    }
+}
+
+impl FromStr for RcadeButton {
+    type Err = UnknownButton;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        RcadeButton::ALL
+            .into_iter()
+            .find(|button| button.name() == name)
+            .ok_or_else(|| UnknownButton(name.to_string()))
+    }
+}
+
+impl TryFrom<String> for RcadeButton {
+    type Error = UnknownButton;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        name.parse()
+    }
+}
+
+impl From<RcadeButton> for String {
+    fn from(button: RcadeButton) -> Self {
+        button.name().to_string()
+    }
+}
+
+/// A button name that doesn't match any [`RcadeButton::name`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnknownButton(pub String);
+
+impl std::fmt::Display for UnknownButton {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unknown controller button `{}`", self.0)
+    }
+}
+
+impl std::error::Error for UnknownButton {}
+
+/// Raw state of the classic controller for the current frame.
+///
+/// Game systems should read `ButtonInput<RcadeButton>` instead, which also knows what was
+/// pressed or released since the last frame.
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq, Deref, DerefMut)]
+pub struct ControllerInput(pub ControllerState);
+
+impl Default for ControllerInput {
+    fn default() -> Self {
+        ControllerInput(ControllerState {
+            connected: false,
+            system_one_player: false,
+            system_two_player: false,
+            player1_up: false,
+            player1_down: false,
+            player1_left: false,
+            player1_right: false,
+            player1_a: false,
+            player1_b: false,
+            player2_up: false,
+            player2_down: false,
+            player2_left: false,
+            player2_right: false,
+            player2_a: false,
+            player2_b: false,
+        })
+    }
+}
+
+/// A source of classic controller state.
+pub trait ControllerBackend {
+    /// Reads the controller state for this frame.
+    fn state(&mut self) -> ControllerState;
+
+    /// Whether there is a usable controller behind this backend.
+    fn is_connected(&self) -> bool {
+        true
+    }
+}
+
+/// The cabinet's classic controller, read through its RCade plugin.
+pub struct ClassicBackend {
+    controller: ClassicController,
+    /// Taken from the latest state, since every read locks the plugin's shared memory.
+    connected: bool,
+}
+
+impl ClassicBackend {
+    pub fn new(controller: ClassicController) -> Self {
+        let connected = controller.state().connected;
+
+        Self {
+            controller,
+            connected,
+        }
+    }
+}
+
+impl ControllerBackend for ClassicBackend {
+    fn state(&mut self) -> ControllerState {
+        let state = self.controller.state();
+
+        self.connected = state.connected;
+
+        state
+    }
+
+    /// Whether the plugin reported a controller plugged in when it was last read. It can be
+    /// unplugged at any time.
+    fn is_connected(&self) -> bool {
+        self.connected
+    }
+}
+
+/// Reports neutral input forever. Stands in for a controller that could not be acquired.
+pub struct NeutralController;
+
+impl ControllerBackend for NeutralController {
+    fn state(&mut self) -> ControllerState {
+        ControllerInput::default().0
+    }
+
+    fn is_connected(&self) -> bool {
+        false
+    }
+}
+
+/// A controller driven by a script instead of hardware.
+///
+/// Clones share the same script, so a test can keep one handle and give the other to the app.
+#[derive(Clone, Default)]
+pub struct MockController {
+    script: Rc<RefCell<MockScript>>,
+}
+
+#[derive(Default)]
+struct MockScript {
+    held: ControllerInput,
+    queued: VecDeque<ControllerState>,
+    disconnected: bool,
+}
+
+impl MockController {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reports `state` from now on, once any queued frames have played out.
+    pub fn hold(&self, state: ControllerState) {
+        self.script.borrow_mut().held = ControllerInput(state);
+    }
+
+    /// Reports `state` for the next `frames` frames.
+    pub fn queue(&self, frames: u32, state: ControllerState) {
+        let mut script = self.script.borrow_mut();
+        script.queued.extend((0..frames).map(|_| state));
+    }
+
+    /// Number of queued frames that haven't been played yet.
+    pub fn queued_frames(&self) -> usize {
+        self.script.borrow().queued.len()
+    }
+
+    pub fn set_connected(&self, connected: bool) {
+        self.script.borrow_mut().disconnected = !connected;
+    }
+}
+
+impl ControllerBackend for MockController {
+    fn state(&mut self) -> ControllerState {
+        let mut script = self.script.borrow_mut();
+        script.queued.pop_front().unwrap_or(script.held.0)
+    }
+
+    fn is_connected(&self) -> bool {
+        !self.script.borrow().disconnected
+    }
+}
+
+/// The active [`ControllerBackend`], kept as a non-send resource.
+pub struct Controller(Box<dyn ControllerBackend>);
+
+impl Controller {
+    /// How long the parent gets to hand over the classic controller's plugin channel.
+    pub const ACQUIRE_TIMEOUT: Duration = Duration::from_secs(5);
+
+    pub fn new(backend: impl ControllerBackend + 'static) -> Self {
+        Controller(Box::new(backend))
+    }
+
+    /// Acquires the cabinet's classic controller, falling back to [`NeutralController`] so the
+    /// game keeps running if it isn't available, or the parent doesn't answer within
+    /// [`Controller::ACQUIRE_TIMEOUT`].
+    pub async fn acquire_classic() -> Self {
+        let acquire = async { Some(ClassicController::acquire().await) };
+
+        let timeout = async {
+            gloo_timers::future::sleep(Self::ACQUIRE_TIMEOUT).await;
+            None
+        };
+
+        match future::or(acquire, timeout).await {
+            Some(Ok(controller)) => Controller::new(ClassicBackend::new(controller)),
+            Some(Err(e)) => {
+                warn!("Failed to acquire classic controller, input is disabled: {e:?}");
+                Controller::new(NeutralController)
+            }
+            None => {
+                warn!(
+                    "The classic controller wasn't handed over within {:?}, input is disabled",
+                    Self::ACQUIRE_TIMEOUT
+                );
+                Controller::new(NeutralController)
+            }
+        }
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.0.is_connected()
+    }
+}
+
+pub fn sample_controller(
+    controller: Option<NonSendMut<Controller>>,
+    mut input: ResMut<ControllerInput>,
+) {
+    if let Some(mut controller) = controller {
+        input.0 = controller.0.state();
+    }
+}
+
+/// Feeds this frame's [`ControllerInput`] into `ButtonInput<RcadeButton>`.
+///
+/// The resource only counts as changed on frames where a button is pressed or released.
+pub fn update_buttons(input: Res<ControllerInput>, mut buttons: ResMut<ButtonInput<RcadeButton>>) {
+    let mut changed = false;
+
+    let inner = buttons.bypass_change_detection();
+    inner.clear();
+
+    for button in RcadeButton::ALL {
+        let held = button.is_held(&input);
+
+        if held != inner.pressed(button) {
+            changed = true;
+
+            if held {
+                inner.press(button);
+            } else {
+                inner.release(button);
+            }
+        }
+    }
+
+    if changed {
+        buttons.set_changed();
+    }
+}
+
+/// Maps the keyboard onto the classic controller.
+///
+/// Player 1 uses WASD with F and G as A and B, player 2 uses the arrow keys with K and L,
+/// and 1 and 2 are the one and two player buttons.
+pub fn sample_keyboard(keys: Res<ButtonInput<KeyCode>>, mut input: ResMut<ControllerInput>) {
+    input.0 = ControllerState {
+        connected: true,
+        system_one_player: keys.pressed(KeyCode::Digit1),
+        system_two_player: keys.pressed(KeyCode::Digit2),
+        player1_up: keys.pressed(KeyCode::KeyW),
+        player1_down: keys.pressed(KeyCode::KeyS),
+        player1_left: keys.pressed(KeyCode::KeyA),
+        player1_right: keys.pressed(KeyCode::KeyD),
+        player1_a: keys.pressed(KeyCode::KeyF),
+        player1_b: keys.pressed(KeyCode::KeyG),
+        player2_up: keys.pressed(KeyCode::ArrowUp),
+        player2_down: keys.pressed(KeyCode::ArrowDown),
+        player2_left: keys.pressed(KeyCode::ArrowLeft),
+        player2_right: keys.pressed(KeyCode::ArrowRight),
+        player2_a: keys.pressed(KeyCode::KeyK),
+        player2_b: keys.pressed(KeyCode::KeyL),
+    };
+}
+
+/// Marks the notice shown while no controller is connected.
+#[derive(Component)]
+pub struct ControllerNotice;
+
+/// Shows [`ControllerNotice`] while the active [`Controller`] is disconnected.
+pub fn update_controller_notice(
+    mut commands: Commands,
+    controller: Option<NonSend<Controller>>,
+    notices: Query<Entity, With<ControllerNotice>>,
+) {
+    let connected = controller.is_none_or(|controller| controller.is_connected());
+
+    match (connected, notices.is_empty()) {
+        (false, true) => {
+            commands.spawn((
+                Text::new("No controller found - input is disabled"),
+                TextFont {
+                    font_size: 12.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+                BackgroundColor(Color::BLACK.with_alpha(0.6)),
+                Node {
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Px(4.0),
+                    left: Val::Px(4.0),
+                    padding: UiRect::all(Val::Px(2.0)),
+                    ..default()
+                },
+                ControllerNotice,
+            ));
+        }
+        (true, false) => {
+            for entity in &notices {
+                commands.entity(entity).despawn();
+            }
+        }
+        _ => {}
+    }
+}
@@ -1,72 +1,318 @@
+pub mod actions;
+pub mod bridge;
+pub mod camera;
+pub mod config;
+pub mod context;
+pub mod crash;
+pub mod display;
+pub mod frame_loop;
+pub mod headless;
 pub mod hook;
+pub mod input;
+pub mod pause;
 
-use std::f32::consts::PI;
+use std::{f32::consts::PI, time::Duration};
 
 use bevy::{
-    app::PluginsState,
-    asset::RenderAssetUsages,
-    color::palettes::css::SILVER,
-    light::DirectionalLightShadowMap,
-    log::{Level, LogPlugin},
-    prelude::*,
+    app::PluginsState, asset::RenderAssetUsages, color::palettes::css::SILVER, log::LogPlugin,
+    prelude::*, render::renderer::RenderDevice,
 };
 
-use rcade_plugin_input_classic::ClassicController;
-
 use wasm_bindgen::prelude::*;
 
+use web_sys::OffscreenCanvas;
+
 use wgpu::{Extent3d, TextureDimension, TextureFormat};
 
-use crate::hook::{RcadePluginExt, get_offscreen_canvas};
+use crate::{
+    actions::ActionPlugin,
+    bridge::{MessageBridgePlugin, WorkerMessages},
+    camera::{CameraControlPlugin, PlayerView},
+    config::RcadeConfig,
+    context::{ContextLossListener, ContextState, RenderContextLost, RenderContextPlugin},
+    display::{Displays, SecondaryCanvases},
+    frame_loop::{FramePacer, FrameStats, HeartbeatTimer},
+    hook::{
+        BackendPreference, DisplaySizeListener, EarlyMessages, RcadeBackend, RcadeInitError,
+        RcadePluginExt,
+    },
+    input::{Controller, Player, RcadeInputPlugin},
+    pause::{PauseListener, PausePlugin, SetPaused},
+};
 
 #[wasm_bindgen]
 
 pub struct BevyApp {
     app: App,
+
+    canvas: OffscreenCanvas,
+
+    config: RcadeConfig,
+
+    pacer: FramePacer,
+
+    heartbeat: HeartbeatTimer,
+
+    pause: PauseListener,
+
+    paused: bool,
+
+    context: ContextLossListener,
+
+    /// Whether the game has been told the context is lost, and rendering is skipped.
+    context_lost: bool,
+
+    /// Replayed once the plugins have finished building.
+    early_messages: Option<EarlyMessages>,
 }
 
 #[wasm_bindgen(start)]
 
 pub async fn start() {
-    console_error_panic_hook::set_once();
+    run(RcadeConfig::default()).await;
+}
 
-    let mut app = BevyApp::new().await;
+/// Starts the game in the worker with `config`, and runs it until it fails.
+///
+/// Errors are reported to the host rather than returned, so this only returns once the game
+/// has stopped for good.
+pub async fn run(config: RcadeConfig) {
+    crash::install_panic_hook();
+
+    let early_messages = match EarlyMessages::install() {
+        Ok(early_messages) => early_messages,
+        Err(e) => {
+            hook::report_init_error(&RcadeInitError::host(e), None);
+            return;
+        }
+    };
+
+    let (canvas, displays) = match hook::receive_canvas().await {
+        Ok(canvases) => canvases,
+        Err(e) => {
+            hook::report_init_error(&e, None);
+            return;
+        }
+    };
+
+    let mut app = match BevyApp::new(canvas.clone(), displays, config, early_messages).await {
+        Ok(app) => app,
+        Err(e) => {
+            hook::report_init_error(&e, Some(&canvas));
+            return;
+        }
+    };
 
     loop {
-        app.update();
+        let now = frame_loop::next_frame(app.pacer.time_until_next(frame_loop::now())).await;
+
+        // The canvas already has a rendering context, so only the host can show the error
+        if app.context.state() == ContextState::Restored
+            && let Err(e) = app.restore_context().await
+        {
+            hook::report_init_error(&e, None);
+            return;
+        }
 
-        gloo_timers::future::sleep(std::time::Duration::from_nanos(0)).await;
+        app.tick(now);
     }
 }
 
 impl BevyApp {
-    pub async fn new() -> Self {
+    pub async fn new(
+        canvas: OffscreenCanvas,
+        displays: SecondaryCanvases,
+        config: RcadeConfig,
+        early_messages: EarlyMessages,
+    ) -> Result<Self, RcadeInitError> {
         let mut app = App::new();
 
-        let canvas = get_offscreen_canvas().unwrap();
+        let pacer = FramePacer::new(config.target_fps);
+
+        let heartbeat = HeartbeatTimer::new(config.heartbeat_interval);
+
+        let controller = Controller::acquire_classic().await;
+
+        let display_size = DisplaySizeListener::install().map_err(RcadeInitError::host)?;
+
+        let pause = PauseListener::install().map_err(RcadeInitError::host)?;
+
+        let worker_messages = WorkerMessages::install().map_err(RcadeInitError::host)?;
+
+        let context = ContextLossListener::install(&canvas).map_err(RcadeInitError::host)?;
+
+        app.insert_non_send_resource(controller)
+            .insert_non_send_resource(display_size)
+            .insert_non_send_resource(worker_messages);
+
+        Self::build(&mut app, &canvas, &displays, &config).await?;
 
-        let controller = ClassicController::acquire().await.unwrap();
+        Ok(BevyApp {
+            app,
+            canvas,
+            config,
+            pacer,
+            heartbeat,
+            pause,
+            paused: false,
+            context,
+            context_lost: false,
+            early_messages: Some(early_messages),
+        })
+    }
+
+    /// Adds rendering to `canvas` and the secondary `displays`, and the game, to `app`.
+    async fn build(
+        app: &mut App,
+        canvas: &OffscreenCanvas,
+        displays: &SecondaryCanvases,
+        config: &RcadeConfig,
+    ) -> Result<(), RcadeInitError> {
+        let plugins = DefaultPlugins
+            .with_rcade_config(canvas.clone(), config.clone())
+            .await?
+            .set(ImagePlugin::default_nearest());
+
+        app.add_plugins(plugins)
+            .insert_non_send_resource(canvas.clone())
+            .insert_non_send_resource(displays.clone())
+            .init_resource::<Displays>()
+            .init_resource::<FrameStats>()
+            .add_systems(
+                PreStartup,
+                (display::spawn_display_windows, hook::setup_added_window).chain(),
+            )
+            .add_systems(Startup, hook::announce_display)
+            .add_systems(PreUpdate, hook::apply_display_size)
+            .add_plugins(GamePlugin);
 
-        app.add_plugins(
+        Ok(())
+    }
+
+    /// Rebuilds the render world on the restored WebGL2 context, or on a new WebGPU device.
+    ///
+    /// Bevy's renderer can't switch to a new device in place, since pipelines, surfaces and every
+    /// GPU asset belong to the old one. Instead a throwaway app is built on a new device and its
+    /// render world takes the place of the old one, see [`context::adopt_render_world`].
+    async fn restore_context(&mut self) -> Result<(), RcadeInitError> {
+        // Stay on the backend the context was lost on, the canvas can't have another one
+        let backend = match self.app.world().get_resource::<RcadeBackend>() {
+            Some(RcadeBackend::WebGpu) => BackendPreference::WebGpu,
+            Some(RcadeBackend::WebGl2) | None => BackendPreference::WebGl2,
+        };
+
+        // Let go of the old device before asking for a new one
+        context::release_render_world(&mut self.app);
+
+        let config = RcadeConfig {
+            backend,
+            ..self.config.clone()
+        };
+
+        let mut renderer = App::new();
+
+        renderer.add_plugins(
             DefaultPlugins
-                .with_rcade(canvas.clone())
-                .await
+                .with_rcade_config(self.canvas.clone(), config)
+                .await?
                 .set(ImagePlugin::default_nearest())
-                .set(LogPlugin {
-                    level: Level::WARN,
-
-                    ..Default::default()
-                }),
-        )
-        .insert_resource(DirectionalLightShadowMap { size: 512 })
-        .insert_non_send_resource(controller)
-        .insert_non_send_resource(canvas)
-        .add_systems(PreStartup, hook::setup_added_window)
-        .add_systems(Startup, setup)
-        .add_systems(Update, rotate)
-        .add_systems(Update, camera_control_system);
+                // The global logger can only be set up once
+                .disable::<LogPlugin>(),
+        );
+
+        while renderer.plugins_state() == PluginsState::Adding {
+            frame_loop::next_frame(Duration::ZERO).await;
+        }
+
+        renderer.finish();
+        renderer.cleanup();
+
+        context::adopt_render_world(&mut self.app, renderer)?;
+
+        self.context.mark_rebuilt();
+        self.watch_device();
+        self.context_lost = false;
+        self.pacer.reset();
 
-        BevyApp { app }
+        Ok(())
+    }
+
+    /// Has the WebGPU device report being lost, since only WebGL2 has canvas events for it.
+    fn watch_device(&self) {
+        let world = self.app.world();
+
+        if world.get_resource::<RcadeBackend>() == Some(&RcadeBackend::WebGpu)
+            && let Some(device) = world.get_resource::<RenderDevice>()
+        {
+            self.context.watch_device(device.wgpu_device());
+        }
+    }
+
+    /// Runs a frame if one is due at `now`, a display frame timestamp in milliseconds.
+    ///
+    /// While the host has the game paused, no frames are run apart from the one that lets the
+    /// game react to being paused, for example by drawing a pause screen.
+    pub fn tick(&mut self, now: f64) {
+        // Sent while paused too, since the host can't tell a paused app from a stuck one
+        if self.heartbeat.is_due(now) {
+            self.send_heartbeat();
+        }
+
+        let paused = self.pause.is_paused();
+
+        if paused != self.paused {
+            self.paused = paused;
+
+            self.app.world_mut().write_message(SetPaused(paused));
+
+            // Time spent paused shouldn't count as skipped frames
+            self.pacer.reset();
+        } else if paused {
+            return;
+        }
+
+        if self.context.state() == ContextState::Lost && !self.context_lost {
+            self.context_lost = true;
+
+            self.app.world_mut().write_message(RenderContextLost);
+
+            // A lost WebGPU device is never restored, but a new one can be asked for right away
+            if self.app.world().get_resource::<RcadeBackend>() == Some(&RcadeBackend::WebGpu) {
+                self.context.mark_restored();
+            }
+        }
+
+        let Some(timing) = self.pacer.tick(now) else {
+            return;
+        };
+
+        if timing.skipped > 0 {
+            debug!("Frame loop skipped {} frame(s)", timing.skipped);
+        }
+
+        if let Some(mut stats) = self.app.world_mut().get_resource_mut::<FrameStats>() {
+            stats.record(timing);
+        }
+
+        self.update();
+    }
+
+    fn send_heartbeat(&self) {
+        let stats = self
+            .app
+            .world()
+            .get_resource::<FrameStats>()
+            .copied()
+            .unwrap_or_default();
+
+        let message = protocol::Message::Heartbeat {
+            frames: stats.frames,
+            frame_time: stats.last_frame_time.as_secs_f64() * 1000.0,
+        };
+
+        if let Err(e) = hook::post_to_host(&message) {
+            warn!("Failed to send heartbeat to host: {e:?}");
+        }
     }
 
     pub fn update(&mut self) {
@@ -75,13 +321,45 @@ impl BevyApp {
                 self.app.finish();
 
                 self.app.cleanup();
+
+                self.watch_device();
+
+                if let Some(early_messages) = self.early_messages.take()
+                    && let Err(e) = early_messages.replay()
+                {
+                    error!("Failed to replay early messages: {e:?}");
+                }
             }
+        } else if self.context_lost {
+            // Keep the game running, but don't extract or render anything to the lost context
+            self.app.main_mut().update();
         } else {
             self.app.update();
         }
     }
 }
 
+/// The game itself, shared by the browser build and the native runner.
+///
+/// Reads input through [`RcadeInputPlugin`], from the [`Controller`] resource if the embedding
+/// app provides one.
+pub struct GamePlugin;
+
+impl Plugin for GamePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            RcadeInputPlugin,
+            ActionPlugin,
+            PausePlugin,
+            MessageBridgePlugin,
+            RenderContextPlugin,
+            CameraControlPlugin,
+        ))
+        .add_systems(Startup, setup)
+        .add_systems(Update, rotate);
+    }
+}
+
 #[derive(Component)]
 
 pub struct Shape;
@@ -92,6 +370,12 @@ const EXTRUSION_X_EXTENT: f32 = 16.0;
 
 const Z_EXTENT: f32 = 5.0;
 
+/// Middle of the shapes spawned by [`setup`], which the orbit camera circles by default.
+pub const SHAPES_CENTRE: Vec3 = Vec3::new(0.0, 2.0, 0.0);
+
+/// Width and depth of the ground plane, centered on the origin.
+pub const GROUND_SIZE: f32 = 50.0;
+
 pub fn setup(
     mut commands: Commands,
 
@@ -145,7 +429,7 @@ pub fn setup(
             MeshMaterial3d(debug_material.clone()),
             Transform::from_xyz(
                 -SHAPES_X_EXTENT / 2. + i as f32 / (num_shapes - 1) as f32 * SHAPES_X_EXTENT,
-                2.0,
+                SHAPES_CENTRE.y,
                 Z_EXTENT / 2.,
             )
             .with_rotation(Quat::from_rotation_x(-PI / 4.)),
@@ -162,7 +446,7 @@ pub fn setup(
             Transform::from_xyz(
                 -EXTRUSION_X_EXTENT / 2.
                     + i as f32 / (num_extrusions - 1) as f32 * EXTRUSION_X_EXTENT,
-                2.0,
+                SHAPES_CENTRE.y,
                 -Z_EXTENT / 2.,
             )
             .with_rotation(Quat::from_rotation_x(-PI / 4.)),
@@ -188,12 +472,19 @@ pub fn setup(
     // ground plane
 
     commands.spawn((
-        Mesh3d(meshes.add(Plane3d::default().mesh().size(50.0, 50.0).subdivisions(10))),
+        Mesh3d(
+            meshes.add(
+                Plane3d::default()
+                    .mesh()
+                    .size(GROUND_SIZE, GROUND_SIZE)
+                    .subdivisions(10),
+            ),
+        ),
         MeshMaterial3d(materials.add(Color::from(SILVER))),
         Transform::from_xyz(0.0, 0.0, 0.0),
     ));
 
-    commands.spawn((
+    let camera = (
         Camera3d::default(),
         Projection::Perspective(PerspectiveProjection {
             fov: std::f32::consts::FRAC_PI_3, // 60 degrees in radians
@@ -202,6 +493,19 @@ pub fn setup(
         }),
         Transform::from_xyz(0.0, 7., 14.0).looking_at(Vec3::new(0., 1., 0.), Vec3::Y),
         Msaa::Off,
+    );
+
+    commands.spawn((camera.clone(), PlayerView(Player::One)));
+
+    // Only switched on for split screen, see `CameraMode`
+    commands.spawn((
+        camera,
+        Camera {
+            is_active: false,
+            order: 1,
+            ..default()
+        },
+        PlayerView(Player::Two),
     ));
 }
 
@@ -212,7 +516,6 @@ pub fn rotate(mut query: Query<&mut Transform, With<Shape>>, time: Res<Time>) {
 }
 
 /// Creates a colorful test pattern
-
 pub fn uv_debug_texture() -> Image {
     const TEXTURE_SIZE: usize = 8;
 
@@ -242,62 +545,7 @@ pub fn uv_debug_texture() -> Image {
         TextureDimension::D2,
         &texture_data,
         TextureFormat::Rgba8UnormSrgb,
-        RenderAssetUsages::RENDER_WORLD,
+        // Kept in the main world too, so it can be uploaded again after a context loss
+        RenderAssetUsages::default(),
     )
 }
-
-pub fn camera_control_system(
-    controller: NonSend<ClassicController>,
-
-    mut camera_query: Query<&mut Transform, With<Camera3d>>,
-
-    time: Res<Time>,
-) {
-    let state = controller.state();
-
-    if let Ok(mut transform) = camera_query.single_mut() {
-        let move_speed = 5.0 * time.delta_secs();
-
-        let rotate_speed = 2.0 * time.delta_secs();
-
-        // Player 1: Movement (WASD-style)
-
-        let forward = transform.forward();
-
-        let right = transform.right();
-
-        if state.player1_up {
-            transform.translation += forward * move_speed;
-        }
-
-        if state.player1_down {
-            transform.translation -= forward * move_speed;
-        }
-
-        if state.player1_left {
-            transform.translation -= right * move_speed;
-        }
-
-        if state.player1_right {
-            transform.translation += right * move_speed;
-        }
-
-        // Player 2: Rotation (look around)
-
-        if state.player2_left {
-            transform.rotate_y(rotate_speed);
-        }
-
-        if state.player2_right {
-            transform.rotate_y(-rotate_speed);
-        }
-
-        if state.player2_up {
-            transform.rotate_local_x(rotate_speed);
-        }
-
-        if state.player2_down {
-            transform.rotate_local_x(-rotate_speed);
-        }
-    }
-}
@@ -0,0 +1,167 @@
+use std::{cell::Cell, rc::Rc};
+
+use bevy::prelude::*;
+use wasm_bindgen::{JsCast, JsValue, prelude::Closure};
+use web_sys::{DedicatedWorkerGlobalScope, MessageEvent, console};
+
+/// Pauses and resumes the game on [`SetPaused`] requests, freezing `Time<Virtual>` while it is
+/// paused.
+///
+/// Games can show their own pause screen by checking [`RunState`] or reading [`PauseChanged`].
+/// Until they do, a [`PauseNotice`] is shown.
+pub struct PausePlugin;
+
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RunState>()
+            .add_message::<SetPaused>()
+            .add_message::<PauseChanged>()
+            .add_systems(PreUpdate, apply_pause_requests)
+            .add_systems(Update, update_pause_notice);
+    }
+}
+
+/// Whether the game is running or paused.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RunState {
+    #[default]
+    Running,
+    Paused,
+}
+
+impl RunState {
+    pub fn is_paused(self) -> bool {
+        self == RunState::Paused
+    }
+}
+
+/// Asks the game to pause (`true`) or resume (`false`).
+///
+/// In the browser this is sent for the host's `PAUSE` and `RESUME` messages, which it posts
+/// when the page is hidden or loses focus, or when the RCade parent frame asks for a pause.
+#[derive(Message, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SetPaused(pub bool);
+
+/// Sent whenever [`RunState`] changes, with the new state.
+#[derive(Message, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PauseChanged(pub RunState);
+
+/// Applies the latest [`SetPaused`] request of the frame.
+pub fn apply_pause_requests(
+    mut requests: MessageReader<SetPaused>,
+    mut state: ResMut<RunState>,
+    mut time: ResMut<Time<Virtual>>,
+    mut changed: MessageWriter<PauseChanged>,
+) {
+    let Some(&SetPaused(paused)) = requests.read().last() else {
+        return;
+    };
+
+    let next = if paused {
+        RunState::Paused
+    } else {
+        RunState::Running
+    };
+
+    if *state == next {
+        return;
+    }
+
+    *state = next;
+
+    if paused {
+        time.pause();
+    } else {
+        time.unpause();
+    }
+
+    changed.write(PauseChanged(next));
+}
+
+/// Marks the notice shown while the game is paused.
+#[derive(Component)]
+pub struct PauseNotice;
+
+/// Shows [`PauseNotice`] while the game is paused.
+pub fn update_pause_notice(
+    mut commands: Commands,
+    state: Res<RunState>,
+    notices: Query<Entity, With<PauseNotice>>,
+) {
+    match (state.is_paused(), notices.is_empty()) {
+        (true, true) => {
+            commands.spawn((
+                Text::new("Paused"),
+                TextFont {
+                    font_size: 24.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+                BackgroundColor(Color::BLACK.with_alpha(0.6)),
+                Node {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                PauseNotice,
+            ));
+        }
+        (false, false) => {
+            for entity in &notices {
+                commands.entity(entity).despawn();
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Listens for `PAUSE` and `RESUME` messages posted to the worker and remembers the latest one.
+pub struct PauseListener {
+    paused: Rc<Cell<bool>>,
+    on_message: Closure<dyn FnMut(MessageEvent)>,
+}
+
+impl PauseListener {
+    pub fn install() -> Result<Self, JsValue> {
+        let paused = Rc::new(Cell::new(false));
+
+        let on_message = {
+            let paused = paused.clone();
+
+            Closure::wrap(Box::new(move |event: MessageEvent| {
+                match protocol::Message::from_js(&event.data()) {
+                    Ok(Some(protocol::Message::Pause)) => paused.set(true),
+                    Ok(Some(protocol::Message::Resume)) => paused.set(false),
+                    _ => {}
+                }
+            }) as Box<dyn FnMut(MessageEvent)>)
+        };
+
+        js_sys::global()
+            .unchecked_into::<DedicatedWorkerGlobalScope>()
+            .add_event_listener_with_callback("message", on_message.as_ref().unchecked_ref())?;
+
+        Ok(Self { paused, on_message })
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.get()
+    }
+}
+
+impl Drop for PauseListener {
+    fn drop(&mut self) {
+        if let Err(e) = js_sys::global()
+            .unchecked_into::<DedicatedWorkerGlobalScope>()
+            .remove_event_listener_with_callback(
+                "message",
+                self.on_message.as_ref().unchecked_ref(),
+            )
+        {
+            console::error_2(&"Failed to stop listening for pause requests:".into(), &e);
+        }
+    }
+}
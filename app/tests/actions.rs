@@ -0,0 +1,126 @@
+use bevy::prelude::*;
+use main::{
+    actions::{Action, ActionMap, ActionMapError, BUNDLED_BINDINGS},
+    headless::HeadlessApp,
+    input::{ControllerInput, Player, PlayerButton, RcadeButton},
+};
+use rcade_plugin_input_classic::state::ControllerState;
+
+fn neutral() -> ControllerState {
+    ControllerInput::default().0
+}
+
+#[test]
+fn bundled_schemes_parse() {
+    let schemes = ActionMap::schemes_from_json(BUNDLED_BINDINGS).unwrap();
+
+    assert!(schemes.contains_key("default"));
+    assert!(schemes.contains_key("one_stick"));
+
+    let default = ActionMap::default();
+    assert_eq!(
+        default.bindings(Action::MoveForward),
+        [RcadeButton::Player(Player::One, PlayerButton::Up)]
+    );
+    assert_eq!(default.bindings(Action::Confirm).len(), 2);
+}
+
+#[test]
+fn bundled_schemes_give_each_button_one_action() {
+    for (scheme, map) in ActionMap::schemes_from_json(BUNDLED_BINDINGS).unwrap() {
+        for button in RcadeButton::ALL {
+            let actions = Action::ALL
+                .into_iter()
+                .filter(|&action| map.bindings(action).contains(&button))
+                .collect::<Vec<_>>();
+
+            assert!(
+                actions.len() <= 1,
+                "`{scheme}` binds {button:?} to {actions:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn unknown_buttons_and_schemes_are_rejected() {
+    assert!(matches!(
+        ActionMap::from_json(r#"{ "x": { "Confirm": ["player3_a"] } }"#, "x"),
+        Err(ActionMapError::Parse(_))
+    ));
+    assert!(matches!(
+        ActionMap::bundled("missing"),
+        Err(ActionMapError::UnknownScheme(_))
+    ));
+}
+
+#[test]
+fn bindings_round_trip_through_json() {
+    let map = ActionMap::bundled("one_stick").unwrap();
+
+    let json = format!(r#"{{ "saved": {} }}"#, map.to_json());
+
+    assert_eq!(ActionMap::from_json(&json, "saved").unwrap(), map);
+}
+
+#[test]
+fn rebinding_changes_which_buttons_move_the_camera() {
+    let mut app = HeadlessApp::new();
+
+    let start = app.camera_transform();
+    let player2_up = ControllerState {
+        player2_up: true,
+        ..neutral()
+    };
+
+    app.world_mut().resource_mut::<ActionMap>().rebind(
+        Action::MoveForward,
+        [RcadeButton::Player(Player::Two, PlayerButton::Up)],
+    );
+    app.world_mut()
+        .resource_mut::<ActionMap>()
+        .rebind(Action::LookUp, []);
+
+    app.run_script(&[(60, player2_up)]);
+
+    let moved = app.camera_transform().translation - start.translation;
+    assert!((moved.length() - 5.0).abs() < 0.1, "moved {moved}");
+}
+
+#[test]
+fn action_edges_with_several_bindings() {
+    let mut app = HeadlessApp::new();
+
+    let confirm = |app: &HeadlessApp| {
+        let actions = app.world().resource::<ButtonInput<Action>>();
+        (
+            actions.pressed(Action::Confirm),
+            actions.just_pressed(Action::Confirm),
+        )
+    };
+
+    app.controller().queue(
+        1,
+        ControllerState {
+            player1_a: true,
+            ..neutral()
+        },
+    );
+    app.controller().queue(
+        1,
+        ControllerState {
+            player2_a: true,
+            ..neutral()
+        },
+    );
+
+    app.step(1);
+    assert_eq!(confirm(&app), (true, true));
+
+    // Switching from one binding to another keeps the action held
+    app.step(1);
+    assert_eq!(confirm(&app), (true, false));
+
+    app.step(1);
+    assert_eq!(confirm(&app), (false, false));
+}
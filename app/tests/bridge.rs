@@ -0,0 +1,62 @@
+use bevy::prelude::Messages;
+use main::{
+    bridge::{FromParent, ToParent},
+    headless::HeadlessApp,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+enum LauncherMessage {
+    HighScore { name: String, score: u32 },
+}
+
+#[test]
+fn messages_round_trip_through_json() {
+    let sent = LauncherMessage::HighScore {
+        name: "ROSE".to_string(),
+        score: 9001,
+    };
+
+    let message = ToParent::new(&sent).unwrap();
+
+    assert_eq!(
+        message.data,
+        json!({ "type": "HighScore", "name": "ROSE", "score": 9001 })
+    );
+
+    let received = FromParent {
+        data: message.data,
+        ports: None,
+    };
+
+    assert_eq!(received.parse::<LauncherMessage>().unwrap(), sent);
+    assert!(received.parse::<u32>().is_err());
+}
+
+#[test]
+fn messages_the_host_would_drop_are_refused() {
+    #[derive(Serialize)]
+    struct Untagged {
+        score: u32,
+    }
+
+    assert!(ToParent::new(&42).is_err());
+    assert!(ToParent::new(&"HighScore").is_err());
+    assert!(ToParent::new(&Untagged { score: 9001 }).is_err());
+    assert!(ToParent::new(&json!({ "type": 7 })).is_err());
+    assert!(ToParent::new(&json!({ "type": "Ping" })).is_ok());
+}
+
+#[test]
+fn bridge_is_inert_without_a_worker() {
+    let mut app = HeadlessApp::new();
+
+    app.world_mut().write_message(ToParent {
+        data: json!({ "type": "Ping" }),
+    });
+    app.step(2);
+
+    assert!(app.world().resource::<Messages<FromParent>>().is_empty());
+}
@@ -0,0 +1,266 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+use main::{
+    SHAPES_CENTRE,
+    camera::{
+        CameraBounds, CameraConstraints, CameraMode, ControlStyle, OrbitAngles, PlayerView,
+        SplitLayout,
+    },
+    headless::HeadlessApp,
+    input::{ControllerInput, Player},
+};
+use rcade_plugin_input_classic::state::ControllerState;
+
+fn neutral() -> ControllerState {
+    ControllerInput::default().0
+}
+
+fn one_player() -> ControllerState {
+    ControllerState {
+        system_one_player: true,
+        ..neutral()
+    }
+}
+
+fn two_player() -> ControllerState {
+    ControllerState {
+        system_two_player: true,
+        ..neutral()
+    }
+}
+
+fn place_camera(app: &mut HeadlessApp, placed: Transform) {
+    for (mut transform, view) in app
+        .world_mut()
+        .query::<(&mut Transform, &PlayerView)>()
+        .iter_mut(app.world_mut())
+    {
+        if view.0 == Player::One {
+            *transform = placed;
+        }
+    }
+}
+
+fn forward() -> ControllerState {
+    ControllerState {
+        player1_up: true,
+        ..neutral()
+    }
+}
+
+#[test]
+fn split_layouts_cover_the_screen() {
+    let size = UVec2::new(336, 262);
+
+    let [left, right] = SplitLayout::SideBySide.viewports(size);
+    assert_eq!(left.physical_position, UVec2::ZERO);
+    assert_eq!(left.physical_size, UVec2::new(168, 262));
+    assert_eq!(right.physical_position, UVec2::new(168, 0));
+    assert_eq!(right.physical_size, UVec2::new(168, 262));
+
+    let [top, bottom] = SplitLayout::Stacked.viewports(UVec2::new(336, 263));
+    assert_eq!(top.physical_size, UVec2::new(336, 131));
+    assert_eq!(bottom.physical_position, UVec2::new(0, 131));
+    assert_eq!(bottom.physical_size, UVec2::new(336, 132));
+}
+
+#[test]
+fn two_player_button_toggles_split_screen() {
+    let mut app = HeadlessApp::new();
+
+    app.world_mut().spawn((
+        Window {
+            resolution: (336, 262).into(),
+            ..default()
+        },
+        PrimaryWindow,
+    ));
+
+    assert!(!app.player_camera(Player::Two).1.is_active);
+
+    app.run_script(&[(1, two_player()), (1, neutral())]);
+
+    assert_eq!(*app.world().resource::<CameraMode>(), CameraMode::Split);
+
+    let (_, first) = app.player_camera(Player::One);
+    let (_, second) = app.player_camera(Player::Two);
+    assert!(first.is_active && second.is_active);
+    assert_eq!(
+        second.viewport.map(|viewport| viewport.physical_position),
+        Some(UVec2::new(168, 0))
+    );
+
+    app.run_script(&[(1, two_player()), (1, neutral())]);
+
+    assert_eq!(*app.world().resource::<CameraMode>(), CameraMode::Shared);
+
+    let (_, first) = app.player_camera(Player::One);
+    let (_, second) = app.player_camera(Player::Two);
+    assert!(first.viewport.is_none());
+    assert!(!second.is_active);
+}
+
+#[test]
+fn each_player_steers_their_own_camera_in_split_screen() {
+    let mut app = HeadlessApp::new();
+
+    app.world_mut().insert_resource(CameraMode::Split);
+
+    let first = app.player_camera(Player::One).0;
+    let second = app.player_camera(Player::Two).0;
+
+    app.run_script(&[(
+        60,
+        ControllerState {
+            player2_up: true,
+            ..neutral()
+        },
+    )]);
+
+    assert_eq!(app.player_camera(Player::One).0, first);
+
+    let moved = app.player_camera(Player::Two).0.translation - second.translation;
+    assert!((moved.length() - 5.0).abs() < 0.1, "moved {moved}");
+}
+
+#[test]
+fn orbit_angles_round_trip() {
+    let offset = Vec3::new(3.0, 4.0, -5.0);
+
+    let angles = OrbitAngles::from_offset(offset);
+
+    assert!(angles.offset().abs_diff_eq(offset, 1e-4), "{angles:?}");
+}
+
+#[test]
+fn orbit_circles_the_shapes_and_eases_back_to_free_fly() {
+    let mut app = HeadlessApp::new();
+
+    let distance =
+        |app: &mut HeadlessApp| app.camera_transform().translation.distance(SHAPES_CENTRE);
+
+    let start = app.camera_transform();
+    let start_distance = distance(&mut app);
+
+    app.run_script(&[(1, one_player()), (1, neutral())]);
+
+    assert_eq!(*app.world().resource::<ControlStyle>(), ControlStyle::Orbit);
+
+    // Switching doesn't move the camera, it only starts turning towards the focus
+    assert!((distance(&mut app) - start_distance).abs() < 0.01);
+
+    app.run_script(&[(
+        60,
+        ControllerState {
+            player2_right: true,
+            ..neutral()
+        },
+    )]);
+
+    let camera = app.camera_transform();
+    assert!((distance(&mut app) - start_distance).abs() < 0.01);
+    assert!(
+        camera.translation.x > start.translation.x + 1.0,
+        "{camera:?}"
+    );
+
+    // Looking straight at the focus once the damping has settled
+    app.step(60);
+
+    let camera = app.camera_transform();
+    let towards = (SHAPES_CENTRE - camera.translation).normalize();
+    assert!(camera.forward().dot(towards) > 0.999);
+
+    app.run_script(&[(
+        60,
+        ControllerState {
+            player1_up: true,
+            ..neutral()
+        },
+    )]);
+    app.step(60);
+
+    assert!(distance(&mut app) < start_distance - 2.0);
+
+    // Back to free flight from wherever the orbit left the camera
+    let orbited = app.camera_transform();
+
+    app.run_script(&[(1, one_player()), (1, neutral())]);
+
+    assert_eq!(
+        *app.world().resource::<ControlStyle>(),
+        ControlStyle::FreeFly
+    );
+    assert_eq!(app.camera_transform(), orbited);
+}
+
+#[test]
+fn looking_up_stops_before_flipping_over() {
+    let mut app = HeadlessApp::new();
+
+    app.run_script(&[(
+        120,
+        ControllerState {
+            player2_up: true,
+            ..neutral()
+        },
+    )]);
+
+    let camera = app.camera_transform();
+    assert!(camera.forward().y <= 1.4f32.sin() + 1e-4, "{camera:?}");
+    assert!(camera.up().y > 0.0, "{camera:?}");
+}
+
+#[test]
+fn camera_stays_above_the_ground_and_inside_the_bounds() {
+    let mut app = HeadlessApp::new();
+
+    place_camera(
+        &mut app,
+        Transform::from_xyz(20.0, 3.0, 0.0).looking_to(Vec3::new(1.0, -1.0, 0.0), Vec3::Y),
+    );
+    app.run_script(&[(120, forward())]);
+
+    let constraints = *app.world().resource::<CameraConstraints>();
+    let camera = app.camera_transform();
+    assert!((camera.translation.y - constraints.min_height).abs() < 1e-4);
+    assert!((camera.translation.x - 25.0).abs() < 1e-4);
+
+    app.world_mut().insert_resource(CameraConstraints {
+        bounds: Some(CameraBounds::Sphere {
+            centre: Vec3::new(0.0, 5.0, 0.0),
+            radius: 10.0,
+        }),
+        ..constraints
+    });
+
+    app.step(1);
+
+    let from_centre = app.camera_transform().translation - Vec3::new(0.0, 5.0, 0.0);
+    assert!(from_centre.length() <= 10.0 + 1e-4, "{from_centre}");
+}
+
+#[test]
+fn shapes_keep_the_camera_out() {
+    let mut app = HeadlessApp::new();
+
+    // The middle cone of the front row sits at (0, 2, 2.5)
+    let cone = Vec3::new(0.0, SHAPES_CENTRE.y, 2.5);
+    let start = Transform::from_xyz(0.0, SHAPES_CENTRE.y, 6.0).looking_to(Vec3::NEG_Z, Vec3::Y);
+
+    // 3.5 units at 5 units per second, right into the cone
+    let script = [(42, forward())];
+
+    place_camera(&mut app, start);
+    app.run_script(&script);
+
+    assert!(app.camera_transform().translation.distance(cone) > 1.0);
+
+    app.world_mut()
+        .resource_mut::<CameraConstraints>()
+        .collide_with_shapes = false;
+
+    place_camera(&mut app, start);
+    app.run_script(&script);
+
+    assert!(app.camera_transform().translation.distance(cone) < 0.1);
+}
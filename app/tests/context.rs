@@ -0,0 +1,257 @@
+use std::{collections::HashSet, time::Instant};
+
+use bevy::{
+    app::SubApp,
+    asset::AssetEvent,
+    ecs::message::MessageCursor,
+    prelude::*,
+    render::{
+        RenderApp,
+        sync_world::{MainEntity, RenderEntity, SyncToRenderWorld},
+    },
+    shader::Shader,
+    time::{TimeReceiver, TimeSender},
+};
+use main::{
+    Shape,
+    actions::{Action, ActionMap},
+    camera::PlayerView,
+    context::{RenderContextLost, RenderContextRestored, ResyncedAssets},
+    headless::HeadlessApp,
+    hook::RcadeInitError,
+    input::{ControllerInput, Player, PlayerButton, RcadeButton},
+};
+use rcade_plugin_input_classic::state::ControllerState;
+
+fn shapes(app: &mut HeadlessApp) -> Vec<Transform> {
+    app.world_mut()
+        .query_filtered::<&Transform, With<Shape>>()
+        .iter(app.world())
+        .copied()
+        .collect()
+}
+
+#[test]
+fn game_can_read_context_messages() {
+    let mut app = HeadlessApp::new();
+
+    let mut lost = app
+        .world()
+        .resource::<Messages<RenderContextLost>>()
+        .get_cursor();
+    let mut restored = app
+        .world()
+        .resource::<Messages<RenderContextRestored>>()
+        .get_cursor();
+
+    app.world_mut().write_message(RenderContextLost);
+    app.step(1);
+
+    let world = app.world();
+
+    assert_eq!(
+        lost.read(world.resource::<Messages<RenderContextLost>>())
+            .count(),
+        1
+    );
+    assert_eq!(
+        restored
+            .read(world.resource::<Messages<RenderContextRestored>>())
+            .count(),
+        0
+    );
+}
+
+#[test]
+fn game_state_survives_losing_and_restoring_the_context() {
+    let mut app = HeadlessApp::new();
+
+    app.world_mut().resource_mut::<ActionMap>().rebind(
+        Action::MoveForward,
+        [RcadeButton::Player(Player::One, PlayerButton::A)],
+    );
+
+    let start = app.camera_transform();
+
+    app.run_script(&[(
+        20,
+        ControllerState {
+            player1_a: true,
+            ..ControllerInput::default().0
+        },
+    )]);
+
+    let bindings = app.world().resource::<ActionMap>().clone();
+    let camera = app.camera_transform();
+    assert_ne!(camera, start);
+
+    let mut restored = app
+        .world()
+        .resource::<Messages<RenderContextRestored>>()
+        .get_cursor();
+
+    // Ok -> Lost: the game keeps running without rendering
+    app.lose_context();
+    app.step(10);
+
+    let while_lost = shapes(&mut app);
+
+    // Lost -> Restored: the same world carries on
+    app.restore_context(renderer()).unwrap();
+    app.step(1);
+
+    assert_eq!(
+        restored
+            .read(app.world().resource::<Messages<RenderContextRestored>>())
+            .count(),
+        1
+    );
+    assert_eq!(*app.world().resource::<ActionMap>(), bindings);
+    assert_eq!(app.camera_transform(), camera);
+
+    let after = shapes(&mut app);
+    assert_eq!(after.len(), while_lost.len());
+    assert!(
+        after
+            .iter()
+            .zip(&while_lost)
+            .all(|(after, before)| after.translation == before.translation
+                && after.rotation != before.rotation),
+        "the shapes should keep turning from where they were"
+    );
+}
+
+/// What the stand-in renderer extracted to its render world.
+#[derive(Resource, Default)]
+struct Extracted {
+    meshes: MessageCursor<AssetEvent<Mesh>>,
+    mesh_ids: HashSet<AssetId<Mesh>>,
+}
+
+/// An app with a render world that extracts like Bevy's: it spawns a render entity for each
+/// entity that wasn't synced yet, and records the meshes that were added or modified.
+fn renderer() -> App {
+    let mut render_app = SubApp::new();
+
+    render_app.init_resource::<Extracted>();
+    render_app.set_extract(|main: &mut World, render: &mut World| {
+        let unsynced = main
+            .query_filtered::<Entity, (With<SyncToRenderWorld>, Without<RenderEntity>)>()
+            .iter(main)
+            .collect::<Vec<_>>();
+
+        for entity in unsynced {
+            let render_entity = render.spawn(MainEntity::from(entity)).id();
+
+            main.entity_mut(entity)
+                .insert(RenderEntity::from(render_entity));
+        }
+
+        let mut extracted = render.resource_mut::<Extracted>();
+        let Extracted { meshes, mesh_ids } = &mut *extracted;
+
+        for event in meshes.read(main.resource::<Messages<AssetEvent<Mesh>>>()) {
+            if let AssetEvent::Added { id } | AssetEvent::Modified { id } = event {
+                mesh_ids.insert(*id);
+            }
+        }
+    });
+
+    let mut renderer = App::empty();
+    renderer.insert_sub_app(RenderApp, render_app);
+    renderer
+}
+
+fn render_entities(app: &mut HeadlessApp) -> HashSet<Entity> {
+    let render_world = app.render_world().expect("a render world");
+
+    render_world
+        .try_query::<&MainEntity>()
+        .expect("synced entities")
+        .iter(render_world)
+        .map(|entity| entity.id())
+        .collect()
+}
+
+#[test]
+fn restoring_the_context_extracts_everything_again() {
+    let mut app = HeadlessApp::new();
+
+    // There are no render plugins here to make these synced
+    let synced = app
+        .world_mut()
+        .query_filtered::<Entity, Or<(With<Shape>, With<PlayerView>)>>()
+        .iter(app.world())
+        .collect::<HashSet<_>>();
+
+    for &entity in &synced {
+        app.world_mut().entity_mut(entity).insert(SyncToRenderWorld);
+    }
+
+    app.restore_context(renderer()).unwrap();
+    app.step(1);
+
+    assert_eq!(render_entities(&mut app), synced);
+
+    app.lose_context();
+    app.step(10);
+
+    let texture = app
+        .world()
+        .resource::<AssetServer>()
+        .load::<Image>("texture.png");
+
+    app.restore_context(renderer()).unwrap();
+    app.step(1);
+
+    // Everything reaches the new render world, not only what changed since the loss
+    assert_eq!(render_entities(&mut app), synced);
+
+    let meshes = app
+        .world()
+        .resource::<Assets<Mesh>>()
+        .ids()
+        .collect::<HashSet<_>>();
+    let extracted = app.render_world().unwrap().resource::<Extracted>();
+    assert!(!meshes.is_empty());
+    assert_eq!(extracted.mesh_ids, meshes);
+
+    // The render world loads through the game's asset server
+    let render_world = app.render_world().unwrap();
+    assert!(
+        render_world
+            .resource::<AssetServer>()
+            .get_path(&texture)
+            .is_some()
+    );
+
+    // And sends its frame times to the game's clock
+    render_world
+        .resource::<TimeSender>()
+        .0
+        .try_send(Instant::now())
+        .unwrap();
+    assert!(app.world().resource::<TimeReceiver>().0.try_recv().is_ok());
+}
+
+#[test]
+fn every_render_asset_type_is_resynced() {
+    let app = HeadlessApp::new();
+    let resynced = app.world().resource::<ResyncedAssets>();
+
+    assert!(resynced.contains::<Shader>());
+    assert!(resynced.contains::<Image>());
+    assert!(resynced.contains::<Mesh>());
+    assert!(resynced.contains::<StandardMaterial>());
+    assert!(resynced.contains::<ColorMaterial>());
+}
+
+#[test]
+fn restoring_needs_a_render_world() {
+    let mut app = HeadlessApp::new();
+
+    assert!(matches!(
+        app.restore_context(App::empty()),
+        Err(RcadeInitError::MissingRenderWorld)
+    ));
+}
@@ -0,0 +1,143 @@
+use std::time::Duration;
+
+use main::{
+    config::RcadeConfig,
+    frame_loop::{FramePacer, FrameStats, FrameTiming, HeartbeatTimer},
+};
+
+/// Timestamps of a 60Hz display, in milliseconds.
+fn display_frames(count: usize) -> impl Iterator<Item = f64> {
+    (0..count).map(|i| i as f64 * 1000.0 / 60.0)
+}
+
+#[test]
+fn runs_every_display_frame_at_matching_target() {
+    let mut pacer = FramePacer::new(60.0);
+
+    let run = display_frames(60)
+        .filter_map(|now| pacer.tick(now))
+        .collect::<Vec<_>>();
+
+    assert_eq!(run.len(), 60);
+    assert!(run.iter().all(|timing| timing.skipped == 0));
+}
+
+#[test]
+fn caps_frame_rate_below_display_rate() {
+    let mut pacer = FramePacer::new(30.0);
+
+    let run = display_frames(60).filter_map(|now| pacer.tick(now)).count();
+
+    assert_eq!(run, 30);
+}
+
+#[test]
+fn keeps_target_rate_on_displays_that_are_not_a_multiple() {
+    let mut pacer = FramePacer::new(60.0);
+
+    // One second of a 144Hz display
+    let run = (0..144)
+        .map(|i| i as f64 * 1000.0 / 144.0)
+        .filter_map(|now| pacer.tick(now))
+        .collect::<Vec<_>>();
+
+    assert_eq!(run.len(), 60);
+    assert!(run.iter().all(|timing| timing.skipped == 0));
+}
+
+#[test]
+fn carries_on_from_a_stall_without_catching_up() {
+    let mut pacer = FramePacer::new(60.0);
+
+    pacer.tick(0.0);
+
+    assert_eq!(pacer.tick(1000.0).map(|timing| timing.skipped), Some(59));
+    assert!(pacer.tick(1000.0 + 1000.0 / 144.0).is_none());
+    assert!(pacer.tick(1000.0 + 1000.0 / 60.0).is_some());
+}
+
+#[test]
+fn tolerates_jitter() {
+    let mut pacer = FramePacer::new(60.0);
+
+    pacer.tick(0.0);
+
+    assert!(pacer.tick(16.0).is_some());
+    assert!(pacer.tick(33.9).is_some());
+}
+
+#[test]
+fn reports_skipped_frames() {
+    let mut pacer = FramePacer::new(60.0);
+    let mut stats = FrameStats::default();
+
+    for now in [0.0, 1000.0 / 60.0, 4000.0 / 60.0, 5000.0 / 60.0] {
+        if let Some(timing) = pacer.tick(now) {
+            stats.record(timing);
+        }
+    }
+
+    assert_eq!(stats.frames, 4);
+    assert_eq!(stats.skipped_frames, 2);
+    assert!((stats.last_frame_time.as_secs_f64() - 1.0 / 60.0).abs() < 1e-6);
+}
+
+#[test]
+fn first_frame_runs_immediately() {
+    let mut pacer = FramePacer::new(60.0);
+
+    assert_eq!(pacer.time_until_next(5.0), Duration::ZERO);
+    assert_eq!(
+        pacer.tick(5.0),
+        Some(FrameTiming {
+            delta: Duration::ZERO,
+            skipped: 0
+        })
+    );
+    assert!(pacer.time_until_next(5.0) > Duration::from_millis(16));
+}
+
+#[test]
+fn reset_runs_next_frame_without_skips() {
+    let mut pacer = FramePacer::new(60.0);
+
+    pacer.tick(0.0);
+    pacer.reset();
+
+    assert_eq!(
+        pacer.tick(5000.0),
+        Some(FrameTiming {
+            delta: Duration::ZERO,
+            skipped: 0
+        })
+    );
+}
+
+#[test]
+#[should_panic(expected = "target frame rate")]
+fn zero_target_fps_is_rejected() {
+    let _ = RcadeConfig::default().with_target_fps(0.0);
+}
+
+#[test]
+#[should_panic(expected = "target frame rate")]
+fn negative_target_fps_is_rejected() {
+    let _ = RcadeConfig::default().with_target_fps(-60.0);
+}
+
+#[test]
+#[should_panic(expected = "target frame rate")]
+fn nan_target_fps_is_rejected() {
+    FramePacer::new(f64::NAN);
+}
+
+#[test]
+fn heartbeats_are_sent_once_per_interval() {
+    let mut heartbeat = HeartbeatTimer::new(Duration::from_secs(1));
+
+    let sent = display_frames(150)
+        .filter(|&now| heartbeat.is_due(now))
+        .collect::<Vec<_>>();
+
+    assert_eq!(sent, [0.0, 1000.0, 2000.0]);
+}
@@ -0,0 +1,233 @@
+use bevy::prelude::*;
+use main::{
+    Shape,
+    actions::Action,
+    headless::HeadlessApp,
+    input::{
+        ControllerBackend, ControllerInput, ControllerNotice, MockController, NeutralController,
+        Player, PlayerButton, RcadeButton,
+    },
+};
+use rcade_plugin_input_classic::state::ControllerState;
+
+fn neutral() -> ControllerState {
+    ControllerInput::default().0
+}
+
+#[test]
+fn holding_player1_up_moves_camera_forward() {
+    let mut app = HeadlessApp::new();
+
+    let start = app.camera_transform();
+
+    app.run_script(&[(
+        60,
+        ControllerState {
+            player1_up: true,
+            ..neutral()
+        },
+    )]);
+
+    let end = app.camera_transform();
+    let moved = end.translation - start.translation;
+
+    // 5 units per second for one second, straight along the view direction
+    assert!((moved.length() - 5.0).abs() < 0.1, "moved {moved}");
+    assert!(moved.normalize().dot(*start.forward()) > 0.999);
+    assert_eq!(start.rotation, end.rotation);
+}
+
+#[test]
+fn neutral_input_leaves_camera_alone() {
+    let mut app = HeadlessApp::new();
+
+    let start = app.camera_transform();
+
+    app.run_script(&[(30, neutral())]);
+
+    assert_eq!(start, app.camera_transform());
+}
+
+#[test]
+fn player2_left_turns_camera() {
+    let mut app = HeadlessApp::new();
+
+    let start = app.camera_transform();
+
+    app.run_script(&[(
+        30,
+        ControllerState {
+            player2_left: true,
+            ..neutral()
+        },
+    )]);
+
+    let end = app.camera_transform();
+
+    assert_eq!(start.translation, end.translation);
+    // 2 radians per second for half a second
+    let turned = start.rotation.angle_between(end.rotation);
+    assert!((turned - 1.0).abs() < 0.01, "turned {turned}");
+}
+
+#[test]
+fn shapes_rotate_at_half_a_radian_per_second() {
+    let mut app = HeadlessApp::new();
+
+    let rotations = |app: &mut HeadlessApp| {
+        app.world_mut()
+            .query_filtered::<&Transform, With<Shape>>()
+            .iter(app.world())
+            .map(|transform| transform.rotation)
+            .collect::<Vec<_>>()
+    };
+
+    let before = rotations(&mut app);
+
+    app.step(120);
+
+    let after = rotations(&mut app);
+
+    assert_eq!(before.len(), 18);
+
+    for (before, after) in before.iter().zip(&after) {
+        let turned = before.angle_between(*after);
+        assert!((turned - 1.0).abs() < 0.01, "turned {turned}");
+    }
+}
+
+#[test]
+fn notice_follows_controller_connection() {
+    let mut app = HeadlessApp::new();
+
+    let notices = |app: &mut HeadlessApp| {
+        app.world_mut()
+            .query_filtered::<(), With<ControllerNotice>>()
+            .iter(app.world())
+            .count()
+    };
+
+    assert_eq!(notices(&mut app), 0);
+
+    app.controller().set_connected(false);
+    app.step(2);
+
+    assert_eq!(notices(&mut app), 1);
+
+    app.controller().set_connected(true);
+    app.step(2);
+
+    assert_eq!(notices(&mut app), 0);
+}
+
+#[test]
+fn mock_controller_plays_queued_frames_before_held_state() {
+    let mut controller = MockController::new();
+
+    let up = ControllerState {
+        player1_up: true,
+        ..neutral()
+    };
+    let down = ControllerState {
+        player1_down: true,
+        ..neutral()
+    };
+
+    controller.hold(down);
+    controller.queue(2, up);
+
+    assert_eq!(controller.state(), up);
+    assert_eq!(controller.state(), up);
+    assert_eq!(controller.state(), down);
+    assert_eq!(controller.state(), down);
+    assert_eq!(controller.queued_frames(), 0);
+}
+
+#[test]
+fn neutral_controller_reports_nothing_pressed() {
+    let mut controller = NeutralController;
+
+    assert!(!controller.is_connected());
+    assert_eq!(controller.state(), neutral());
+}
+
+#[test]
+fn buttons_report_press_and_release_edges() {
+    let mut app = HeadlessApp::new();
+
+    let a = RcadeButton::Player(Player::Two, PlayerButton::A);
+    let buttons = |app: &HeadlessApp| {
+        let buttons = app.world().resource::<ButtonInput<RcadeButton>>();
+        (
+            buttons.pressed(a),
+            buttons.just_pressed(a),
+            buttons.just_released(a),
+        )
+    };
+
+    app.controller().queue(
+        2,
+        ControllerState {
+            player2_a: true,
+            ..neutral()
+        },
+    );
+
+    app.step(1);
+    assert_eq!(buttons(&app), (true, true, false));
+
+    app.step(1);
+    assert_eq!(buttons(&app), (true, false, false));
+
+    app.step(1);
+    assert_eq!(buttons(&app), (false, false, true));
+
+    app.step(1);
+    assert_eq!(buttons(&app), (false, false, false));
+}
+
+#[test]
+fn buttons_only_change_when_one_is_pressed_or_released() {
+    let mut app = HeadlessApp::new();
+
+    let changed = |app: &HeadlessApp| {
+        let world = app.world();
+        (
+            world
+                .get_resource_change_ticks::<ButtonInput<RcadeButton>>()
+                .expect("buttons")
+                .changed,
+            world
+                .get_resource_change_ticks::<ButtonInput<Action>>()
+                .expect("actions")
+                .changed,
+        )
+    };
+
+    app.step(1);
+    let idle = changed(&app);
+
+    app.step(1);
+    assert_eq!(changed(&app), idle, "nothing was pressed or released");
+
+    app.controller().queue(
+        1,
+        ControllerState {
+            player1_up: true,
+            ..neutral()
+        },
+    );
+    app.step(1);
+
+    let pressed = changed(&app);
+    assert_ne!(pressed.0, idle.0);
+    assert_ne!(pressed.1, idle.1);
+
+    // Released
+    app.step(1);
+    let released = changed(&app);
+    assert_ne!(released, pressed);
+
+    app.step(1);
+    assert_eq!(changed(&app), released);
+}
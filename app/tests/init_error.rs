@@ -0,0 +1,29 @@
+use main::hook::RcadeInitError;
+use protocol::{ErrorKind, Message};
+
+#[test]
+fn init_errors_are_reported_to_the_host() {
+    let errors = [
+        RcadeInitError::MissingCanvas,
+        RcadeInitError::Context("WebGL2 is unavailable".to_string()),
+        RcadeInitError::WebGpuUnsupported,
+    ];
+
+    for error in errors {
+        // The host shows these over the canvas, since the app can't always draw them itself
+        assert_eq!(
+            error.to_message(),
+            Message::Error {
+                kind: ErrorKind::Init,
+                message: error.to_string(),
+                location: None,
+                backtrace: None,
+            }
+        );
+    }
+
+    assert_eq!(
+        RcadeInitError::Context("WebGL2 is unavailable".to_string()).to_string(),
+        "Failed to create WebGL2 context: WebGL2 is unavailable"
+    );
+}
@@ -0,0 +1,96 @@
+use bevy::prelude::*;
+use main::{
+    Shape,
+    headless::HeadlessApp,
+    input::ControllerInput,
+    pause::{PauseChanged, PauseNotice, RunState, SetPaused},
+};
+use rcade_plugin_input_classic::state::ControllerState;
+
+fn shape_rotation(app: &mut HeadlessApp) -> Quat {
+    app.world_mut()
+        .query_filtered::<&Transform, With<Shape>>()
+        .iter(app.world())
+        .next()
+        .expect("at least one Shape")
+        .rotation
+}
+
+fn notices(app: &mut HeadlessApp) -> usize {
+    app.world_mut()
+        .query_filtered::<(), With<PauseNotice>>()
+        .iter(app.world())
+        .count()
+}
+
+#[test]
+fn pausing_freezes_the_game() {
+    let mut app = HeadlessApp::new();
+
+    app.world_mut().write_message(SetPaused(true));
+    app.step(1);
+
+    let rotation = shape_rotation(&mut app);
+    let camera = app.camera_transform();
+
+    app.run_script(&[(
+        30,
+        ControllerState {
+            player1_up: true,
+            ..ControllerInput::default().0
+        },
+    )]);
+
+    assert_eq!(*app.world().resource::<RunState>(), RunState::Paused);
+    assert_eq!(rotation, shape_rotation(&mut app));
+    assert_eq!(camera, app.camera_transform());
+}
+
+#[test]
+fn resuming_picks_up_where_it_left_off() {
+    let mut app = HeadlessApp::new();
+
+    app.world_mut().write_message(SetPaused(true));
+    app.step(10);
+
+    let rotation = shape_rotation(&mut app);
+
+    app.world_mut().write_message(SetPaused(false));
+    app.step(60);
+
+    assert_eq!(*app.world().resource::<RunState>(), RunState::Running);
+
+    // Half a radian per second, with the resume frame itself still frozen
+    let turned = rotation.angle_between(shape_rotation(&mut app));
+    assert!((turned - 59.0 / 120.0).abs() < 0.01, "turned {turned}");
+}
+
+#[test]
+fn notice_and_messages_follow_pause_state() {
+    let mut app = HeadlessApp::new();
+
+    let changes = |app: &mut HeadlessApp| {
+        app.world_mut()
+            .resource_mut::<Messages<PauseChanged>>()
+            .drain()
+            .collect::<Vec<_>>()
+    };
+
+    app.world_mut().write_message(SetPaused(true));
+    app.step(2);
+
+    assert_eq!(notices(&mut app), 1);
+    assert_eq!(changes(&mut app), [PauseChanged(RunState::Paused)]);
+
+    // Repeated requests don't count as changes
+    app.world_mut().write_message(SetPaused(true));
+    app.step(1);
+
+    assert_eq!(changes(&mut app), []);
+
+    app.world_mut().write_message(SetPaused(false));
+    app.step(2);
+
+    assert_eq!(notices(&mut app), 0);
+    assert_eq!(changes(&mut app), [PauseChanged(RunState::Running)]);
+}
@@ -1,5 +1,8 @@
-use wasm_bindgen::{JsCast, JsValue};
-use web_sys::HtmlCanvasElement;
+use std::{cell::RefCell, rc::Rc};
+
+use protocol::{Border, Message, NamedCanvas, Scaling};
+use wasm_bindgen::{JsCast, JsValue, prelude::Closure};
+use web_sys::{AddEventListenerOptions, HtmlCanvasElement, OffscreenCanvas};
 
 /// Helper function to create the canvas and set up its styles.
 pub fn create_and_setup_canvas() -> Result<HtmlCanvasElement, JsValue> {
@@ -37,10 +40,287 @@ pub fn create_and_setup_canvas() -> Result<HtmlCanvasElement, JsValue> {
     // 5. Append the canvas to the document body
     body.append_child(&canvas)?;
 
-    canvas.set_width(canvas.client_width() as u32);
-    canvas.set_height(canvas.client_height() as u32);
+    // Size the backing store in physical pixels so high-DPI displays aren't blurry
+    let size = DisplaySize::of(&canvas);
+    canvas.set_width(size.width);
+    canvas.set_height(size.height);
 
     web_sys::console::debug_1(&"Canvas created and appended successfully!".into());
 
     Ok(canvas)
 }
+
+/// Swaps `canvas` for a new one and hands back control of it.
+///
+/// Control of a canvas can only be transferred once, so an app started again needs a new one.
+pub fn replace_canvas(canvas: &RefCell<HtmlCanvasElement>) -> Result<OffscreenCanvas, JsValue> {
+    let new_canvas = create_and_setup_canvas()?;
+
+    canvas.replace(new_canvas).remove();
+
+    canvas.borrow().transfer_control_to_offscreen()
+}
+
+/// Hands back control of the page's canvases for secondary displays, each named by its
+/// `data-rcade-display` attribute:
+///
+/// ```html
+/// <canvas data-rcade-display="marquee" style="width: 640px; height: 160px"></canvas>
+/// ```
+///
+/// The page lays these out itself, and their backing store is sized to their displayed size
+/// once, when they are handed over. With `fresh`, each canvas is first swapped for a copy of
+/// itself, since control of one can only be transferred once.
+pub fn display_canvases(fresh: bool) -> Result<Vec<NamedCanvas>, JsValue> {
+    let document = web_sys::window()
+        .and_then(|window| window.document())
+        .ok_or_else(|| JsValue::from_str("Document not found"))?;
+
+    let found = document.query_selector_all("canvas[data-rcade-display]")?;
+    let mut displays = Vec::new();
+
+    for index in 0..found.length() {
+        let Some(mut canvas) = found
+            .item(index)
+            .and_then(|node| node.dyn_into::<HtmlCanvasElement>().ok())
+        else {
+            continue;
+        };
+
+        if fresh {
+            let copy = canvas.clone_node()?.dyn_into::<HtmlCanvasElement>()?;
+            canvas.replace_with_with_node_1(&copy)?;
+            canvas = copy;
+        }
+
+        let name = canvas
+            .get_attribute("data-rcade-display")
+            .unwrap_or_default();
+
+        let size = DisplaySize::of(&canvas);
+        canvas.set_width(size.width);
+        canvas.set_height(size.height);
+
+        displays.push(NamedCanvas {
+            name,
+            canvas: canvas.transfer_control_to_offscreen()?,
+        });
+    }
+
+    Ok(displays)
+}
+
+/// The size the canvas is displayed at, in physical pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DisplaySize {
+    pub width: u32,
+    pub height: u32,
+    pub scale_factor: f64,
+}
+
+impl DisplaySize {
+    /// Measures the on-screen size of `canvas` using the current device pixel ratio.
+    pub fn of(canvas: &HtmlCanvasElement) -> Self {
+        let scale_factor = web_sys::window()
+            .map(|window| window.device_pixel_ratio())
+            .unwrap_or(1.0);
+
+        Self {
+            width: (canvas.client_width() as f64 * scale_factor)
+                .round()
+                .max(1.0) as u32,
+            height: (canvas.client_height() as f64 * scale_factor)
+                .round()
+                .max(1.0) as u32,
+            scale_factor,
+        }
+    }
+
+    /// Builds the `RESIZE` message sent to the worker.
+    pub fn to_message(&self) -> Message {
+        Message::Resize {
+            width: self.width,
+            height: self.height,
+            scale_factor: self.scale_factor,
+        }
+    }
+}
+
+/// How the canvas is fitted into the page, as requested by the app's `INIT` message.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum ScalingMode {
+    /// Stretch the canvas over the whole viewport, ignoring the aspect ratio.
+    #[default]
+    Stretch,
+    /// Keep the aspect ratio of the app's logical resolution, scaling by whole numbers where
+    /// the viewport is large enough, and letterbox the rest of the page with `border`.
+    IntegerScale {
+        width: u32,
+        height: u32,
+        border: Border,
+    },
+}
+
+impl ScalingMode {
+    /// Styles `canvas` and the page background to fit the current viewport.
+    pub fn apply(&self, canvas: &HtmlCanvasElement) -> Result<(), JsValue> {
+        let window = web_sys::window().ok_or_else(|| JsValue::from_str("Window not found"))?;
+        let body = window
+            .document()
+            .and_then(|document| document.body())
+            .ok_or_else(|| JsValue::from_str("Body not found"))?;
+
+        let style = canvas.style();
+        let body_style = body.style();
+
+        match self {
+            ScalingMode::Stretch => {
+                style.set_property("width", "100vw")?;
+                style.set_property("height", "100vh")?;
+                style.remove_property("position")?;
+                style.remove_property("left")?;
+                style.remove_property("top")?;
+                body_style.remove_property("background")?;
+            }
+            ScalingMode::IntegerScale {
+                width,
+                height,
+                border,
+            } => {
+                let scale_factor = window.device_pixel_ratio();
+                let viewport_width = window.inner_width()?.as_f64().unwrap_or(0.0) * scale_factor;
+                let viewport_height = window.inner_height()?.as_f64().unwrap_or(0.0) * scale_factor;
+
+                let scale = integer_scale(
+                    (viewport_width, viewport_height),
+                    (*width as f64, *height as f64),
+                );
+
+                // Sizes are computed in physical pixels and converted back to CSS pixels so
+                // that each logical pixel covers a whole number of screen pixels.
+                let scaled_width = *width as f64 * scale;
+                let scaled_height = *height as f64 * scale;
+
+                // Centred by whole physical pixels too, as centring with a transform lands on a
+                // half pixel whenever the space left over is odd, which blurs the canvas.
+                let left = ((viewport_width - scaled_width) / 2.0).floor().max(0.0);
+                let top = ((viewport_height - scaled_height) / 2.0).floor().max(0.0);
+
+                style.set_property("width", &format!("{}px", scaled_width / scale_factor))?;
+                style.set_property("height", &format!("{}px", scaled_height / scale_factor))?;
+                style.set_property("position", "absolute")?;
+                style.set_property("left", &format!("{}px", left / scale_factor))?;
+                style.set_property("top", &format!("{}px", top / scale_factor))?;
+
+                match border {
+                    Border::Color(color) => body_style.set_property("background", color)?,
+                    Border::Image(url) => body_style
+                        .set_property("background", &format!("{} center / cover", css_url(url)))?,
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl From<Scaling> for ScalingMode {
+    fn from(scaling: Scaling) -> Self {
+        match scaling {
+            Scaling::Stretch => ScalingMode::Stretch,
+            Scaling::Integer {
+                width,
+                height,
+                border,
+            } => ScalingMode::IntegerScale {
+                width,
+                height,
+                border,
+            },
+        }
+    }
+}
+
+/// A CSS `url()` for `url`, quoted and escaped so that no URL can end the value early or add
+/// properties of its own.
+fn css_url(url: &str) -> String {
+    let mut quoted = String::with_capacity(url.len() + 7);
+
+    quoted.push_str("url(\"");
+
+    for c in url.chars() {
+        match c {
+            '"' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            // Control characters, newlines included, can only appear as hex escapes
+            c if c.is_control() => quoted.push_str(&format!("\\{:x} ", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+
+    quoted.push_str("\")");
+
+    quoted
+}
+
+/// Largest whole-number scale at which `logical` fits inside `viewport`.
+///
+/// Falls back to the largest fractional scale that preserves the aspect ratio when the viewport
+/// is smaller than the logical resolution. `logical` must not be zero in either direction, which
+/// `INIT` messages are checked for.
+pub fn integer_scale(viewport: (f64, f64), logical: (f64, f64)) -> f64 {
+    let fit = (viewport.0 / logical.0).min(viewport.1 / logical.1);
+
+    if fit >= 1.0 { fit.floor() } else { fit }
+}
+
+/// Calls `on_change` whenever the viewport is resized or the device pixel ratio changes.
+///
+/// Window resizes (including browser zoom and cabinet rotation) are picked up through the
+/// `resize` event, while moving between monitors with different pixel densities is detected
+/// with a `(resolution: Ndppx)` media query that is re-armed after every change.
+pub fn watch_display(on_change: impl FnMut() + 'static) -> Result<(), JsValue> {
+    let window = web_sys::window().ok_or_else(|| JsValue::from_str("Window not found"))?;
+
+    let on_change = RefCell::new(on_change);
+    let notify: Rc<dyn Fn()> = Rc::new(move || (on_change.borrow_mut())());
+
+    let on_resize = {
+        let notify = notify.clone();
+        Closure::wrap(Box::new(move || notify()) as Box<dyn Fn()>)
+    };
+
+    window.add_event_listener_with_callback("resize", on_resize.as_ref().unchecked_ref())?;
+    on_resize.forget();
+
+    watch_pixel_ratio(notify)
+}
+
+fn watch_pixel_ratio(notify: Rc<dyn Fn()>) -> Result<(), JsValue> {
+    let window = web_sys::window().ok_or_else(|| JsValue::from_str("Window not found"))?;
+
+    let query = format!("(resolution: {}dppx)", window.device_pixel_ratio());
+    let Some(media_query) = window.match_media(&query)? else {
+        // No media query support, so we only get to hear about window resizes.
+        return Ok(());
+    };
+
+    let on_change = Closure::once_into_js(move || {
+        notify();
+
+        if let Err(e) = watch_pixel_ratio(notify) {
+            web_sys::console::error_2(&"Failed to watch device pixel ratio:".into(), &e);
+        }
+    });
+
+    let options = AddEventListenerOptions::new();
+    options.set_once(true);
+
+    media_query.add_event_listener_with_callback_and_add_event_listener_options(
+        "change",
+        on_change.unchecked_ref(),
+        &options,
+    )
+}
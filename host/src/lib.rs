@@ -1,39 +1,236 @@
 pub mod canvas;
+pub mod overlay;
+pub mod pause;
+pub mod policy;
+pub mod restart;
+pub mod watchdog;
+pub mod worker;
 
-use js_sys::{Array, Function, Object, Reflect}; // Added Function here
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
+use js_sys::{Date, Function};
+use protocol::{ErrorKind, LogLevel, Message};
 use wasm_bindgen::JsCast;
 use wasm_bindgen::prelude::*;
+use web_sys::MessageEvent;
 use web_sys::console;
-use web_sys::{MessageEvent, Worker, WorkerOptions, WorkerType};
 
-use crate::canvas::create_and_setup_canvas;
+use crate::{
+    canvas::{
+        DisplaySize, ScalingMode, create_and_setup_canvas, display_canvases, replace_canvas,
+        watch_display,
+    },
+    overlay::Overlay,
+    pause::{PausePolicy, PauseReasons, watch_page_activity},
+    policy::{MessagePolicy, Rejection, RejectionLog, check_shape},
+    restart::{PendingRestart, RestartPolicy},
+    watchdog::{LastHeartbeat, Watchdog, every},
+    worker::{WorkerChannel, spawn_worker},
+};
 
 #[wasm_bindgen(start)]
 pub fn start() -> Result<(), JsValue> {
     web_sys::console::debug_1(&"Main started!".into());
 
-    let canvas = create_and_setup_canvas().unwrap();
-    let offscreen_canvas = canvas.transfer_control_to_offscreen().unwrap();
+    // Both are replaced when the app is restarted. The `CANVAS` message is sent once the worker
+    // says it is ready, since it can't hear anything before that.
+    let canvas = Rc::new(RefCell::new(create_and_setup_canvas().unwrap()));
+    let canvas_message = Rc::new(RefCell::new(Some(Message::Canvas {
+        canvas: canvas.borrow().transfer_control_to_offscreen().unwrap(),
+        displays: display_canvases(false)?,
+    })));
     web_sys::console::debug_1(&"Canvas control transferred to OffscreenCanvas.".into());
 
-    let options = WorkerOptions::new();
-    options.set_type(WorkerType::Classic);
-    options.set_name("App");
+    // Covers the canvas if the app crashes
+    let overlay = Rc::new(Overlay::create()?);
+
+    let worker = Rc::new(WorkerChannel::new(spawn_worker().unwrap()));
+
+    // Lays the canvas out for the current scaling mode and tells the worker when the
+    // resulting display size changes.
+    let scaling = Rc::new(RefCell::new(ScalingMode::default()));
+
+    let last_size = Rc::new(Cell::new(Some(DisplaySize::of(&canvas.borrow()))));
+
+    let refresh_display: Rc<dyn Fn()> = {
+        let canvas = canvas.clone();
+        let scaling = scaling.clone();
+        let worker = worker.clone();
+        let last_size = last_size.clone();
+
+        Rc::new(move || {
+            let canvas = canvas.borrow();
+
+            if let Err(e) = scaling.borrow().apply(&canvas) {
+                web_sys::console::error_2(&"Failed to lay out canvas:".into(), &e);
+            }
+
+            let size = DisplaySize::of(&canvas);
+
+            if Some(size) == last_size.get() {
+                return;
+            }
+
+            last_size.set(Some(size));
+
+            if let Err(e) = worker.post(&size.to_message()) {
+                web_sys::console::error_2(&"Failed to send resize message to worker:".into(), &e);
+            }
+        })
+    };
+
+    // Notices when the app's frame loop gets stuck
+    let watchdog = Rc::new(Watchdog::from_page());
+
+    console::debug_2(&"Watchdog:".into(), &format!("{watchdog:?}").into());
+
+    // Pauses the game while the page is hidden, unfocused if the page opts in, or the parent
+    // frame asked for it, and tells the worker whenever that changes.
+    let pause_policy = PausePolicy::from_page();
+    let pause_requested = Rc::new(Cell::new(false));
+    let was_paused = Rc::new(Cell::new(false));
+
+    let refresh_pause: Rc<dyn Fn()> = {
+        let pause_requested = pause_requested.clone();
+        let worker = worker.clone();
+        let was_paused = was_paused.clone();
+        let watchdog = watchdog.clone();
+
+        Rc::new(move || {
+            let reasons = PauseReasons::observe(pause_requested.get(), pause_policy);
+
+            if reasons.is_paused() == was_paused.get() {
+                return;
+            }
+
+            was_paused.set(reasons.is_paused());
+
+            // A paused app may not get to run frames, so only time spent running counts
+            watchdog.reset(Date::now());
+
+            let message = if reasons.is_paused() {
+                Message::Pause
+            } else {
+                Message::Resume
+            };
+
+            console::debug_2(
+                &"Pause state changed:".into(),
+                &format!("{reasons:?}").into(),
+            );
+
+            if let Err(e) = worker.post(&message) {
+                web_sys::console::error_2(&"Failed to send pause message to worker:".into(), &e);
+            }
+        })
+    };
+
+    // Starts the app over in a new worker, on a new canvas, after a crash or when the parent
+    // frame asks for it. The new app starts out with the default layout and unpaused, so both
+    // are sent to it again.
+    let restart_policy = RestartPolicy::from_page();
+    let pending_restart = Rc::new(PendingRestart::default());
+
+    console::debug_2(
+        &"Restart policy:".into(),
+        &format!("{restart_policy:?}").into(),
+    );
+
+    let restart: Rc<dyn Fn()> = {
+        let canvas = canvas.clone();
+        let canvas_message = canvas_message.clone();
+        let overlay = overlay.clone();
+        let worker = worker.clone();
+        let scaling = scaling.clone();
+        let refresh_display = refresh_display.clone();
+        let refresh_pause = refresh_pause.clone();
+        let pending_restart = pending_restart.clone();
+        let watchdog = watchdog.clone();
+        let was_paused = was_paused.clone();
+
+        Rc::new(move || {
+            pending_restart.cancel();
+            watchdog.disarm();
+
+            console::info_1(&"Restarting the app.".into());
 
-    let worker = Worker::new_with_options("./worker.js", &options).unwrap();
+            let new_worker = match replace_canvas(&canvas).and_then(|offscreen| {
+                *canvas_message.borrow_mut() = Some(Message::Canvas {
+                    canvas: offscreen,
+                    displays: display_canvases(true)?,
+                });
+                spawn_worker()
+            }) {
+                Ok(new_worker) => new_worker,
+                Err(e) => {
+                    console::error_2(&"Failed to restart the app:".into(), &e);
+                    return;
+                }
+            };
+
+            worker.replace(new_worker);
+
+            *scaling.borrow_mut() = ScalingMode::default();
+            last_size.set(None);
+            was_paused.set(false);
+
+            refresh_display();
+            refresh_pause();
+
+            if let Err(e) = overlay.hide() {
+                console::error_2(&"Failed to hide overlay:".into(), &e);
+            }
+        })
+    };
+
+    // Only messages from trusted origins, in a shape we understand, are passed on
+    let policy = Rc::new(MessagePolicy::from_page());
+    let rejections = Rc::new(RejectionLog::default());
+
+    console::debug_2(&"Message policy:".into(), &format!("{policy:?}").into());
 
     // --- 1. Forward Window Messages to Worker (With Transferables) ---
 
     let worker_clone = worker.clone();
+    let refresh_pause_clone = refresh_pause.clone();
+    let restart_clone = restart.clone();
+    let policy_clone = policy.clone();
+    let rejections_clone = rejections.clone();
 
     let on_window_msg = Closure::wrap(Box::new(move |event: MessageEvent| {
         let data = event.data();
         let ports = event.ports();
 
+        match policy_clone.check_from_parent(&event.origin(), &data) {
+            // Pause requests are combined with the page's own state before reaching the worker
+            Ok(Some(message @ (Message::Pause | Message::Resume))) => {
+                pause_requested.set(message == Message::Pause);
+                refresh_pause_clone();
+                return;
+            }
+            Ok(Some(Message::Restart)) => {
+                restart_clone();
+                return;
+            }
+            Ok(Some(message)) => {
+                let rejection = Rejection::NotAllowed(message.kind());
+                rejections_clone.reject("parent", &rejection, &data);
+                return;
+            }
+            Ok(None) => {}
+            Err(rejection) => {
+                rejections_clone.reject("parent", &rejection, &data);
+                return;
+            }
+        }
+
         console::log_3(&"Main -> Worker".into(), &event.data(), &event.ports());
 
-        // Forward to worker
-        if let Err(e) = worker_clone.post_message_with_transfer(&data, &ports) {
+        // Forward to worker, once it is listening
+        if let Err(e) = worker_clone.forward(&data, &ports) {
             web_sys::console::error_2(&"Failed to forward message to worker:".into(), &e);
         }
     }) as Box<dyn FnMut(MessageEvent)>);
@@ -47,36 +244,190 @@ pub fn start() -> Result<(), JsValue> {
     // We capture the window object to post messages back to it
     let window_target = window.clone().parent().unwrap().unwrap();
 
+    // The watchdog reports to the parent too
+    let window_parent = window_target.clone();
+    let policy_parent_origin = policy.parent_origin.clone();
+
+    let refresh_display_clone = refresh_display.clone();
+    let worker_clone = worker.clone();
+    let overlay_clone = overlay.clone();
+    let watchdog_clone = watchdog.clone();
+    let pending_restart_clone = pending_restart.clone();
+    let restart_clone = restart.clone();
+
     let on_worker_msg = Closure::wrap(Box::new(move |event: MessageEvent| {
         let data = event.data();
         let ports = event.ports();
 
+        match check_shape(&data) {
+            // The app's READY, INIT and LOG messages are meant for us, not the parent frame
+            Ok(Some(Message::Ready)) => {
+                let Some(canvases) = canvas_message.borrow_mut().take() else {
+                    rejections.reject("app", &Rejection::NotAllowed("repeated READY"), &data);
+                    return;
+                };
+
+                // The canvas has to arrive before anything that was held back for the worker
+                match worker_clone.open(&canvases) {
+                    Ok(()) => console::debug_1(&"Canvases transferred to the worker.".into()),
+                    Err(e) => console::error_2(&"Failed to send canvas to worker:".into(), &e),
+                }
+                return;
+            }
+            Ok(Some(Message::Init { scaling: mode })) => {
+                console::debug_2(&"Applying scaling mode from INIT:".into(), &data);
+                *scaling.borrow_mut() = mode.into();
+                refresh_display_clone();
+                return;
+            }
+            Ok(Some(Message::Heartbeat { frames, frame_time })) => {
+                let heartbeat = LastHeartbeat { frames, frame_time };
+
+                if watchdog_clone.beat(Date::now(), heartbeat) {
+                    console::info_1(&"App is responding again.".into());
+
+                    pending_restart_clone.cancel();
+
+                    if let Err(e) = overlay_clone.hide() {
+                        console::error_2(&"Failed to hide overlay:".into(), &e);
+                    }
+                }
+                return;
+            }
+            Ok(Some(Message::Log { level, message })) => {
+                let message = JsValue::from(format!("[App] {message}"));
+
+                match level {
+                    LogLevel::Error => console::error_1(&message),
+                    LogLevel::Warn => console::warn_1(&message),
+                    LogLevel::Info => console::info_1(&message),
+                    LogLevel::Debug => console::debug_1(&message),
+                }
+                return;
+            }
+            // Errors are passed on too, so the launcher can go back to the menu or restart
+            Ok(Some(Message::Error {
+                kind,
+                message,
+                location,
+                backtrace,
+            })) => {
+                console::error_1(&format!("App reported an error ({kind:?}): {message}").into());
+
+                // The app can't always draw a startup error itself, as a canvas with a WebGL
+                // context can't be drawn on in 2D any more
+                let title = match kind {
+                    ErrorKind::Init => Some("The game couldn't start"),
+                    ErrorKind::Panic => Some("The game crashed"),
+                    ErrorKind::Runtime | ErrorKind::Unresponsive => None,
+                };
+
+                if let Some(title) = title
+                    && let Err(e) = overlay_clone.show(
+                        title,
+                        &message,
+                        location.as_deref(),
+                        backtrace.as_deref(),
+                    )
+                {
+                    console::error_2(&"Failed to show overlay:".into(), &e);
+                }
+
+                if kind == ErrorKind::Panic
+                    && let Some(delay) = restart_policy.auto_restart_delay
+                {
+                    let restart = restart_clone.clone();
+
+                    if let Err(e) = pending_restart_clone.schedule(delay, move || restart()) {
+                        console::error_2(&"Failed to schedule restart:".into(), &e);
+                    }
+                }
+            }
+            Ok(Some(Message::Exit)) | Ok(None) => {}
+            Ok(Some(message)) => {
+                rejections.reject("app", &Rejection::NotAllowed(message.kind()), &data);
+                return;
+            }
+            Err(rejection) => {
+                rejections.reject("app", &rejection, &data);
+                return;
+            }
+        }
+
         console::log_3(&"Worker -> Main".into(), &event.data(), &event.ports());
 
         if ports.length() > 0 {
-            if let Err(e) = window_target.post_message_with_transfer(&data, "*", &ports) {
-                web_sys::console::error_2(&"Failed to forward worker message:".into(), &e);
-            }
-        } else {
-            if let Err(e) = window_target.post_message(&data, "*") {
+            if let Err(e) =
+                window_target.post_message_with_transfer(&data, &policy.parent_origin, &ports)
+            {
                 web_sys::console::error_2(&"Failed to forward worker message:".into(), &e);
             }
+        } else if let Err(e) = window_target.post_message(&data, &policy.parent_origin) {
+            web_sys::console::error_2(&"Failed to forward worker message:".into(), &e);
         }
     }) as Box<dyn FnMut(MessageEvent)>);
 
-    worker.set_onmessage(Some(on_worker_msg.as_ref().unchecked_ref()));
+    worker.listen(on_worker_msg.as_ref().unchecked_ref::<Function>().clone());
     on_worker_msg.forget();
 
-    // --- 3. Initial Setup (Canvas Transfer) ---
+    web_sys::console::debug_1(&"Web Worker spawned, waiting for it to ask for the canvas.".into());
+
+    // --- 3. Keep the layout and the worker in sync with the display ---
 
-    let message_object = Object::new();
-    Reflect::set(&message_object, &"type".into(), &"CANVAS".into())?;
-    Reflect::set(&message_object, &"canvas".into(), &offscreen_canvas)?;
+    watch_display(move || refresh_display())?;
+
+    // --- 4. Pause the game while nobody can see or play it ---
+
+    refresh_pause();
+    watch_page_activity(move || refresh_pause())?;
+
+    // --- 5. Tell the parent, and restart if allowed, when the app stops responding ---
+
+    every(1000, move || {
+        if !watchdog.check(Date::now(), was_paused.get()) {
+            return;
+        }
 
-    let transfer_list = Array::of1(&offscreen_canvas);
-    worker.post_message_with_transfer(&message_object, &transfer_list)?;
+        let heartbeat = watchdog.last_heartbeat();
+        let timeout = watchdog.timeout_ms.unwrap_or_default() / 1000.0;
 
-    web_sys::console::debug_1(&"Web Worker spawned and Canvas transferred.".into());
+        let message = format!(
+            "No heartbeat for {timeout} seconds. The last one was sent after {} frame(s), with \
+             {:.1} ms between the last two.",
+            heartbeat.frames, heartbeat.frame_time
+        );
+
+        console::error_1(&format!("App is not responding: {message}").into());
+
+        if let Err(e) = overlay.show("The game isn't responding", &message, None, None) {
+            console::error_2(&"Failed to show overlay:".into(), &e);
+        }
+
+        let report = Message::Error {
+            kind: ErrorKind::Unresponsive,
+            message,
+            location: None,
+            backtrace: None,
+        };
+
+        if let Err(e) = report
+            .to_js()
+            .and_then(|report| window_parent.post_message(&report, &policy_parent_origin))
+        {
+            console::error_2(
+                &"Failed to tell parent the app isn't responding:".into(),
+                &e,
+            );
+        }
+
+        if let Some(delay) = restart_policy.auto_restart_delay {
+            let restart = restart.clone();
+
+            if let Err(e) = pending_restart.schedule(delay, move || restart()) {
+                console::error_2(&"Failed to schedule restart:".into(), &e);
+            }
+        }
+    })?;
 
     Ok(())
 }
@@ -0,0 +1,86 @@
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{Document, HtmlElement};
+
+/// A panel laid over the canvas, explaining why the game stopped.
+///
+/// Everything shown is set as text, so whatever the app reports can't inject markup.
+pub struct Overlay {
+    document: Document,
+    root: HtmlElement,
+}
+
+impl Overlay {
+    /// Adds the overlay, hidden, to the page.
+    pub fn create() -> Result<Self, JsValue> {
+        let document = web_sys::window()
+            .and_then(|window| window.document())
+            .ok_or_else(|| JsValue::from_str("Document not found"))?;
+        let body = document
+            .body()
+            .ok_or_else(|| JsValue::from_str("Body not found"))?;
+
+        let root = document.create_element("div")?.dyn_into::<HtmlElement>()?;
+        root.set_id("gameOverlay");
+
+        let style = root.style();
+        style.set_property("position", "fixed")?;
+        style.set_property("inset", "0")?;
+        style.set_property("z-index", "1")?;
+        style.set_property("overflow", "auto")?;
+        style.set_property("box-sizing", "border-box")?;
+        style.set_property("padding", "5vmin")?;
+        style.set_property("background", "rgba(32, 0, 0, 0.9)")?;
+        style.set_property("color", "white")?;
+        style.set_property("font", "2.5vmin monospace")?;
+        style.set_property("display", "none")?;
+
+        body.append_child(&root)?;
+
+        Ok(Self { document, root })
+    }
+
+    /// Shows `title` and `message`, followed by where the problem happened and a collapsed
+    /// backtrace when they are known.
+    pub fn show(
+        &self,
+        title: &str,
+        message: &str,
+        location: Option<&str>,
+        backtrace: Option<&str>,
+    ) -> Result<(), JsValue> {
+        self.root.set_text_content(None);
+
+        let heading = self.append("h1", title, &self.root)?;
+        heading.style().set_property("color", "#ff6666")?;
+
+        let message = self.append("pre", message, &self.root)?;
+        message.style().set_property("white-space", "pre-wrap")?;
+
+        if let Some(location) = location {
+            self.append("p", &format!("at {location}"), &self.root)?;
+        }
+
+        if let Some(backtrace) = backtrace {
+            let details = self.append("details", "", &self.root)?;
+            self.append("summary", "Backtrace", &details)?;
+            self.append("pre", backtrace, &details)?;
+        }
+
+        self.root.style().set_property("display", "block")
+    }
+
+    pub fn hide(&self) -> Result<(), JsValue> {
+        self.root.style().set_property("display", "none")
+    }
+
+    fn append(&self, tag: &str, text: &str, parent: &HtmlElement) -> Result<HtmlElement, JsValue> {
+        let element = self
+            .document
+            .create_element(tag)?
+            .dyn_into::<HtmlElement>()?;
+        element.set_text_content(Some(text));
+        parent.append_child(&element)?;
+
+        Ok(element)
+    }
+}
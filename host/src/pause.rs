@@ -0,0 +1,76 @@
+use wasm_bindgen::{JsCast, JsValue, prelude::Closure};
+
+use crate::policy::meta_content;
+
+/// Which changes to the page pause the game, besides it being hidden.
+///
+/// Pausing when the page loses focus is opt-in with a `<meta>` tag:
+///
+/// ```html
+/// <meta name="rcade-pause-on-blur" content="true">
+/// ```
+///
+/// It is off by default because the RCade frame usually doesn't have focus at all, its input
+/// arriving from the parent frame instead, so the game would start paused.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PausePolicy {
+    pub pause_on_blur: bool,
+}
+
+impl PausePolicy {
+    /// Reads the policy from the host page.
+    pub fn from_page() -> Self {
+        Self {
+            pause_on_blur: meta_content("rcade-pause-on-blur")
+                .is_some_and(|content| content.trim().eq_ignore_ascii_case("true")),
+        }
+    }
+}
+
+/// Everything that currently keeps the game paused. It only runs while none of them apply.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PauseReasons {
+    /// The page is hidden, for example because the tab is in the background.
+    pub hidden: bool,
+    /// The page lost focus, for example because the launcher is drawn over the game. Only
+    /// counted with [`PausePolicy::pause_on_blur`].
+    pub unfocused: bool,
+    /// The parent frame sent a `PAUSE` message and hasn't sent `RESUME` since.
+    pub requested: bool,
+}
+
+impl PauseReasons {
+    /// Reads `hidden` and `unfocused` from the current state of the page.
+    pub fn observe(requested: bool, policy: PausePolicy) -> Self {
+        let document = web_sys::window().and_then(|window| window.document());
+
+        Self {
+            hidden: document.as_ref().is_some_and(|document| document.hidden()),
+            unfocused: policy.pause_on_blur
+                && document.is_some_and(|document| !document.has_focus().unwrap_or(true)),
+            requested,
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.hidden || self.unfocused || self.requested
+    }
+}
+
+/// Calls `on_change` whenever the page is shown, hidden, focused or blurred.
+pub fn watch_page_activity(on_change: impl FnMut() + 'static) -> Result<(), JsValue> {
+    let window = web_sys::window().ok_or_else(|| JsValue::from_str("Window not found"))?;
+    let document = window
+        .document()
+        .ok_or_else(|| JsValue::from_str("Document not found"))?;
+
+    let on_change = Closure::wrap(Box::new(on_change) as Box<dyn FnMut()>);
+
+    document
+        .add_event_listener_with_callback("visibilitychange", on_change.as_ref().unchecked_ref())?;
+    window.add_event_listener_with_callback("focus", on_change.as_ref().unchecked_ref())?;
+    window.add_event_listener_with_callback("blur", on_change.as_ref().unchecked_ref())?;
+    on_change.forget();
+
+    Ok(())
+}
@@ -0,0 +1,163 @@
+use std::cell::Cell;
+
+use js_sys::Reflect;
+use protocol::{Message, ProtocolError};
+use wasm_bindgen::JsValue;
+use web_sys::{Url, console};
+
+/// Which origins the host takes messages from, and which origin it posts to.
+///
+/// Configured with `<meta>` tags in the host page, each holding a space-separated list:
+///
+/// ```html
+/// <meta name="rcade-trusted-origins" content="https://rcade.example https://localhost:5173">
+/// <meta name="rcade-parent-origin" content="https://rcade.example">
+/// ```
+///
+/// Without them, only the origin of the frame embedding the host is trusted and posted to, or
+/// the host's own origin when it isn't embedded. A trusted origin of `*` trusts every origin.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MessagePolicy {
+    pub trusted_origins: Vec<String>,
+    /// Target origin for messages posted to the parent frame.
+    pub parent_origin: String,
+}
+
+impl MessagePolicy {
+    /// Reads the policy from the host page.
+    pub fn from_page() -> Self {
+        let default_origin = embedding_origin().unwrap_or_default();
+
+        let trusted_origins = meta_content("rcade-trusted-origins")
+            .map(|content| content.split_whitespace().map(str::to_string).collect())
+            .unwrap_or_else(|| vec![default_origin.clone()]);
+
+        let parent_origin = meta_content("rcade-parent-origin")
+            .map(|content| content.trim().to_string())
+            .unwrap_or(default_origin);
+
+        Self {
+            trusted_origins,
+            parent_origin,
+        }
+    }
+
+    pub fn is_trusted(&self, origin: &str) -> bool {
+        self.trusted_origins
+            .iter()
+            .any(|trusted| trusted == "*" || trusted == origin)
+    }
+
+    /// Checks a message from the parent frame before it is handled or forwarded.
+    pub fn check_from_parent(
+        &self,
+        origin: &str,
+        data: &JsValue,
+    ) -> Result<Option<Message>, Rejection> {
+        if !self.is_trusted(origin) {
+            return Err(Rejection::UntrustedOrigin(origin.to_string()));
+        }
+
+        check_shape(data)
+    }
+}
+
+/// Origin of the frame embedding the host page, or of the page itself when it is the top frame.
+fn embedding_origin() -> Option<String> {
+    let window = web_sys::window()?;
+    let own_origin = window.location().origin().ok()?;
+
+    let is_embedded = window
+        .parent()
+        .ok()
+        .flatten()
+        .is_some_and(|parent| JsValue::from(parent) != JsValue::from(window.clone()));
+
+    if !is_embedded {
+        return Some(own_origin);
+    }
+
+    // The referrer of a framed page is the page that framed it
+    let referrer = window.document()?.referrer();
+
+    Url::new(&referrer)
+        .map(|url| url.origin())
+        .ok()
+        .or(Some(own_origin))
+}
+
+pub(crate) fn meta_content(name: &str) -> Option<String> {
+    web_sys::window()?
+        .document()?
+        .query_selector(&format!("meta[name=\"{name}\"]"))
+        .ok()??
+        .get_attribute("content")
+}
+
+/// Checks that `data` is either a valid protocol message, or an object with a string `type`
+/// like every other message we pass along.
+pub fn check_shape(data: &JsValue) -> Result<Option<Message>, Rejection> {
+    match Message::from_js(data) {
+        Ok(Some(message)) => Ok(Some(message)),
+        Ok(None) => {
+            if !data.is_object() {
+                return Err(Rejection::Malformed("it isn't an object"));
+            }
+
+            let kind = Reflect::get(data, &"type".into()).unwrap_or(JsValue::UNDEFINED);
+
+            if kind.as_string().is_none() {
+                return Err(Rejection::Malformed("it has no string `type` field"));
+            }
+
+            Ok(None)
+        }
+        Err(e) => Err(Rejection::Protocol(e)),
+    }
+}
+
+/// Why a message wasn't handled or forwarded.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Rejection {
+    UntrustedOrigin(String),
+    Malformed(&'static str),
+    Protocol(ProtocolError),
+    /// A valid protocol message, but not one this sender may send.
+    NotAllowed(&'static str),
+}
+
+impl std::fmt::Display for Rejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Rejection::UntrustedOrigin(origin) => write!(f, "origin `{origin}` isn't trusted"),
+            Rejection::Malformed(reason) => write!(f, "malformed message, {reason}"),
+            Rejection::Protocol(e) => write!(f, "{e}"),
+            Rejection::NotAllowed(kind) => write!(f, "{kind} messages may not be sent from here"),
+        }
+    }
+}
+
+/// Counts and logs rejected messages.
+#[derive(Debug, Default)]
+pub struct RejectionLog {
+    rejected: Cell<u32>,
+}
+
+impl RejectionLog {
+    pub fn reject(&self, source: &str, rejection: &Rejection, data: &JsValue) {
+        self.rejected.set(self.rejected.get() + 1);
+
+        console::warn_2(
+            &format!(
+                "Rejected message from {source}: {rejection} ({} rejected so far)",
+                self.rejected.get()
+            )
+            .into(),
+            data,
+        );
+    }
+
+    pub fn rejected(&self) -> u32 {
+        self.rejected.get()
+    }
+}
@@ -0,0 +1,66 @@
+use std::cell::Cell;
+
+use wasm_bindgen::{JsCast, JsValue, prelude::Closure};
+
+use crate::policy::meta_content;
+
+/// Whether the host restarts the app on its own after it crashes.
+///
+/// Configured with a `<meta>` tag holding how many seconds the crash overlay stays up first:
+///
+/// ```html
+/// <meta name="rcade-auto-restart" content="5">
+/// ```
+///
+/// Without it the app is only restarted when the parent frame sends a `RESTART` message.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RestartPolicy {
+    /// Milliseconds to wait before restarting, or `None` to leave it to the parent frame.
+    pub auto_restart_delay: Option<i32>,
+}
+
+impl RestartPolicy {
+    /// Reads the policy from the host page.
+    pub fn from_page() -> Self {
+        let auto_restart_delay = meta_content("rcade-auto-restart")
+            .and_then(|content| content.trim().parse::<f64>().ok())
+            .filter(|seconds| seconds.is_finite() && *seconds >= 0.0)
+            .map(|seconds| (seconds * 1000.0).round() as i32);
+
+        Self { auto_restart_delay }
+    }
+}
+
+/// A restart waiting on a timer, so it can be called off if the app is restarted sooner.
+#[derive(Debug, Default)]
+pub struct PendingRestart {
+    timeout: Cell<Option<i32>>,
+}
+
+impl PendingRestart {
+    /// Calls `restart` after `delay` milliseconds, replacing any restart already scheduled.
+    pub fn schedule(&self, delay: i32, restart: impl FnOnce() + 'static) -> Result<(), JsValue> {
+        let window = web_sys::window().ok_or_else(|| JsValue::from_str("Window not found"))?;
+
+        self.cancel();
+
+        let restart = Closure::once_into_js(restart);
+
+        let timeout = window.set_timeout_with_callback_and_timeout_and_arguments_0(
+            restart.unchecked_ref(),
+            delay,
+        )?;
+
+        self.timeout.set(Some(timeout));
+
+        Ok(())
+    }
+
+    pub fn cancel(&self) {
+        if let Some(timeout) = self.timeout.take()
+            && let Some(window) = web_sys::window()
+        {
+            window.clear_timeout_with_handle(timeout);
+        }
+    }
+}
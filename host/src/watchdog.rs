@@ -0,0 +1,120 @@
+use std::cell::Cell;
+
+use wasm_bindgen::{JsCast, JsValue, prelude::Closure};
+
+use crate::policy::meta_content;
+
+/// How long the app may go without a heartbeat before it is considered stuck, when the page
+/// doesn't set one.
+pub const DEFAULT_TIMEOUT_MS: f64 = 10_000.0;
+
+/// Notices when the app stops sending `HEARTBEAT` messages, which means its frame loop is stuck.
+///
+/// The timeout is configured with a `<meta>` tag, in seconds, where `0` turns the watchdog off:
+///
+/// ```html
+/// <meta name="rcade-watchdog-timeout" content="10">
+/// ```
+///
+/// The watchdog is only armed once the app's first heartbeat arrives, and doesn't count time
+/// spent paused, since the browser stops running frames for hidden pages.
+#[derive(Debug)]
+pub struct Watchdog {
+    /// Milliseconds without a heartbeat before the app is considered stuck, or `None` if the
+    /// watchdog is off.
+    pub timeout_ms: Option<f64>,
+    last_beat: Cell<Option<f64>>,
+    last_heartbeat: Cell<LastHeartbeat>,
+    tripped: Cell<bool>,
+}
+
+/// What the app reported in its last heartbeat.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LastHeartbeat {
+    pub frames: u64,
+    /// Milliseconds between the app's last two frames.
+    pub frame_time: f64,
+}
+
+impl Watchdog {
+    pub fn new(timeout_ms: Option<f64>) -> Self {
+        Self {
+            timeout_ms,
+            last_beat: Cell::new(None),
+            last_heartbeat: Cell::new(LastHeartbeat::default()),
+            tripped: Cell::new(false),
+        }
+    }
+
+    /// Reads the timeout from the host page.
+    pub fn from_page() -> Self {
+        let timeout_ms = match meta_content("rcade-watchdog-timeout")
+            .and_then(|content| content.trim().parse::<f64>().ok())
+            .filter(|seconds| seconds.is_finite() && *seconds >= 0.0)
+        {
+            Some(0.0) => None,
+            Some(seconds) => Some(seconds * 1000.0),
+            None => Some(DEFAULT_TIMEOUT_MS),
+        };
+
+        Self::new(timeout_ms)
+    }
+
+    /// Records a heartbeat at `now`. Returns whether the watchdog had tripped, meaning the app
+    /// came back after all.
+    pub fn beat(&self, now: f64, heartbeat: LastHeartbeat) -> bool {
+        self.last_beat.set(Some(now));
+        self.last_heartbeat.set(heartbeat);
+
+        self.tripped.replace(false)
+    }
+
+    pub fn last_heartbeat(&self) -> LastHeartbeat {
+        self.last_heartbeat.get()
+    }
+
+    /// Starts counting again from `now`, for when time since the last heartbeat shouldn't count,
+    /// like after a pause.
+    pub fn reset(&self, now: f64) {
+        if self.last_beat.get().is_some() {
+            self.last_beat.set(Some(now));
+        }
+    }
+
+    /// Stops watching until the next heartbeat, for when the app is being replaced.
+    pub fn disarm(&self) {
+        self.last_beat.set(None);
+        self.tripped.set(false);
+    }
+
+    /// Checks the app at `now`. Returns true once when it has gone too long without a
+    /// heartbeat, and false again until it either sends one or is disarmed.
+    pub fn check(&self, now: f64, paused: bool) -> bool {
+        let (Some(timeout_ms), Some(last_beat)) = (self.timeout_ms, self.last_beat.get()) else {
+            return false;
+        };
+
+        if paused || self.tripped.get() || now - last_beat < timeout_ms {
+            return false;
+        }
+
+        self.tripped.set(true);
+
+        true
+    }
+}
+
+/// Calls `on_tick` every `interval_ms` milliseconds, for as long as the page is open.
+pub fn every(interval_ms: i32, on_tick: impl FnMut() + 'static) -> Result<(), JsValue> {
+    let window = web_sys::window().ok_or_else(|| JsValue::from_str("Window not found"))?;
+
+    let on_tick = Closure::wrap(Box::new(on_tick) as Box<dyn FnMut()>);
+
+    window.set_interval_with_callback_and_timeout_and_arguments_0(
+        on_tick.as_ref().unchecked_ref(),
+        interval_ms,
+    )?;
+    on_tick.forget();
+
+    Ok(())
+}
@@ -0,0 +1,102 @@
+use std::cell::RefCell;
+
+use js_sys::{Array, Function};
+use protocol::Message;
+use wasm_bindgen::JsValue;
+use web_sys::{Worker, WorkerOptions, WorkerType, console};
+
+/// The worker loader generated by trunk for the app crate, see `index.html`.
+const WORKER_SCRIPT: &str = "./app/rose-sample-rs_loader.js";
+
+/// Starts a new worker running the app.
+pub fn spawn_worker() -> Result<Worker, JsValue> {
+    let options = WorkerOptions::new();
+    options.set_type(WorkerType::Classic);
+    options.set_name("App");
+
+    Worker::new_with_options(WORKER_SCRIPT, &options)
+}
+
+/// The host's end of the app's worker.
+///
+/// The worker can't hear anything until its wasm module has started, so everything sent to it
+/// is held back until it says it is `READY` and [`WorkerChannel::open`] is called.
+///
+/// The worker can be swapped for a new one with [`WorkerChannel::replace`], which starts holding
+/// messages back again until the new worker is ready.
+pub struct WorkerChannel {
+    worker: RefCell<Worker>,
+    on_message: RefCell<Option<Function>>,
+    held: RefCell<Option<Vec<(JsValue, Array)>>>,
+}
+
+impl WorkerChannel {
+    pub fn new(worker: Worker) -> Self {
+        Self {
+            worker: RefCell::new(worker),
+            on_message: RefCell::new(None),
+            held: RefCell::new(Some(Vec::new())),
+        }
+    }
+
+    /// Calls `on_message` for every message from the worker, and from any that replaces it.
+    pub fn listen(&self, on_message: Function) {
+        self.worker.borrow().set_onmessage(Some(&on_message));
+        *self.on_message.borrow_mut() = Some(on_message);
+    }
+
+    /// Terminates the current worker and carries on with `worker`.
+    ///
+    /// Anything still held back for the old worker is dropped, since it was meant for an app
+    /// that no longer exists.
+    pub fn replace(&self, worker: Worker) {
+        let old = self.worker.replace(worker);
+        old.set_onmessage(None);
+        old.terminate();
+
+        self.worker
+            .borrow()
+            .set_onmessage(self.on_message.borrow().as_ref());
+        *self.held.borrow_mut() = Some(Vec::new());
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.held.borrow().is_none()
+    }
+
+    /// Posts `message` to the worker, transferring anything that can't be copied.
+    pub fn post(&self, message: &Message) -> Result<(), JsValue> {
+        self.forward(&message.to_js()?, &message.transferables())
+    }
+
+    /// Posts `data` to the worker as is, transferring the objects in `transfer`.
+    pub fn forward(&self, data: &JsValue, transfer: &Array) -> Result<(), JsValue> {
+        if let Some(held) = self.held.borrow_mut().as_mut() {
+            held.push((data.clone(), transfer.clone()));
+            return Ok(());
+        }
+
+        self.worker
+            .borrow()
+            .post_message_with_transfer(data, transfer)
+    }
+
+    /// Posts `first`, then everything held back so far, in order, and stops holding messages.
+    pub fn open(&self, first: &Message) -> Result<(), JsValue> {
+        let held = self.held.borrow_mut().take().unwrap_or_default();
+
+        let worker = self.worker.borrow();
+
+        worker.post_message_with_transfer(&first.to_js()?, &first.transferables())?;
+
+        if !held.is_empty() {
+            console::debug_1(&format!("Sending {} held message(s) to worker", held.len()).into());
+        }
+
+        for (data, transfer) in held {
+            worker.post_message_with_transfer(&data, &transfer)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,530 @@
+// Messages exchanged between the host page and the app's worker, and between the host and the
+// RCade parent frame.
+//
+// Every message is a plain object with a `type` field naming the message and a `version` field
+// set to `PROTOCOL_VERSION`, plus the fields of that message. Other traffic, like the RCade
+// SDK's plugin channel messages, shares the same `postMessage` channels and is told apart by not
+// using one of our `type`s and not carrying a `version`.
+
+use js_sys::{Array, Object, Reflect};
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::OffscreenCanvas;
+
+/// Version of the protocol spoken by this build. Messages from any other version are rejected.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// A message of the host/worker protocol.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Message {
+    /// Worker → host: the worker is up and listening, and can be sent the canvas.
+    Ready,
+    /// Host → worker: the canvas to render to, and any canvases for secondary displays. They
+    /// must be transferred along with the message, see [`Message::transferables`].
+    Canvas {
+        canvas: OffscreenCanvas,
+        displays: Vec<NamedCanvas>,
+    },
+    /// Host → worker: the size the canvas is displayed at, in physical pixels.
+    Resize {
+        width: u32,
+        height: u32,
+        scale_factor: f64,
+    },
+    /// Parent → host → worker: stop running the game until [`Message::Resume`].
+    Pause,
+    /// Parent → host → worker: carry on after a [`Message::Pause`].
+    Resume,
+    /// Worker → host: sent once the app has started, telling the host how to lay out the canvas.
+    Init { scaling: Scaling },
+    /// Worker → host: a line for the host's console.
+    Log { level: LogLevel, message: String },
+    /// Worker → host → parent: something went wrong in the app.
+    Error {
+        kind: ErrorKind,
+        message: String,
+        /// Where in the source it went wrong, as `file:line:column`.
+        location: Option<String>,
+        backtrace: Option<String>,
+    },
+    /// Worker → host → parent: the game wants to quit back to the launcher.
+    Exit,
+    /// Parent → host: start the app again in a new worker, on a new canvas.
+    Restart,
+    /// Worker → host: sent regularly while the app's frame loop is running, so the host can
+    /// tell when it stops.
+    Heartbeat {
+        /// Number of frames run so far.
+        frames: u64,
+        /// Time between the last two frames, in milliseconds.
+        frame_time: f64,
+    },
+}
+
+/// A canvas for a secondary display, like a marquee above the main screen.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NamedCanvas {
+    pub name: String,
+    pub canvas: OffscreenCanvas,
+}
+
+/// How the host fits the canvas into the page.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Scaling {
+    /// Stretch the canvas over the whole page, ignoring the aspect ratio.
+    #[default]
+    Stretch,
+    /// Keep the aspect ratio of a fixed logical resolution, scaling it by whole numbers where
+    /// the page is large enough, and letterbox the rest of the page with `border`.
+    Integer {
+        width: u32,
+        height: u32,
+        border: Border,
+    },
+}
+
+/// What fills the letterbox around an integer-scaled canvas.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Border {
+    /// Any CSS colour.
+    Color(String),
+    /// URL of an image that covers the page behind the canvas.
+    Image(String),
+}
+
+impl Default for Border {
+    fn default() -> Self {
+        Border::Color("black".to_string())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The app failed to start, usually because rendering couldn't be set up.
+    Init,
+    /// The app failed while it was running.
+    Runtime,
+    /// The app panicked and has stopped.
+    Panic,
+    /// The app stopped sending heartbeats, and is probably stuck.
+    Unresponsive,
+}
+
+/// Why a message that looked like one of ours was rejected.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ProtocolError {
+    /// The message was sent by a different version of the protocol.
+    UnsupportedVersion(f64),
+    /// The message carries a `version` but its `type` isn't one we know.
+    UnknownType(String),
+    MissingField {
+        message: &'static str,
+        field: &'static str,
+    },
+    InvalidField {
+        message: &'static str,
+        field: &'static str,
+        expected: &'static str,
+    },
+}
+
+impl std::fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProtocolError::UnsupportedVersion(version) => write!(
+                f,
+                "message is from protocol version {version}, but this build speaks version {PROTOCOL_VERSION}"
+            ),
+            ProtocolError::UnknownType(kind) => write!(f, "unknown message type `{kind}`"),
+            ProtocolError::MissingField { message, field } => {
+                write!(f, "{message} message is missing the `{field}` field")
+            }
+            ProtocolError::InvalidField {
+                message,
+                field,
+                expected,
+            } => write!(
+                f,
+                "`{field}` field of {message} message should be {expected}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ProtocolError {}
+
+const TYPES: [&str; 11] = [
+    "READY",
+    "CANVAS",
+    "RESIZE",
+    "PAUSE",
+    "RESUME",
+    "INIT",
+    "LOG",
+    "ERROR",
+    "EXIT",
+    "RESTART",
+    "HEARTBEAT",
+];
+
+impl Message {
+    /// The `type` this message is sent with.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Message::Ready => "READY",
+            Message::Canvas { .. } => "CANVAS",
+            Message::Resize { .. } => "RESIZE",
+            Message::Pause => "PAUSE",
+            Message::Resume => "RESUME",
+            Message::Init { .. } => "INIT",
+            Message::Log { .. } => "LOG",
+            Message::Error { .. } => "ERROR",
+            Message::Exit => "EXIT",
+            Message::Restart => "RESTART",
+            Message::Heartbeat { .. } => "HEARTBEAT",
+        }
+    }
+
+    /// Reads a message out of the data of a `MessageEvent`.
+    ///
+    /// Returns `Ok(None)` for data that isn't part of this protocol and should be left to
+    /// whoever it is meant for, and an error for data that claims to be but doesn't match it.
+    pub fn from_js(data: &JsValue) -> Result<Option<Self>, ProtocolError> {
+        if !data.is_object() {
+            return Ok(None);
+        }
+
+        let get = |name: &str| Reflect::get(data, &name.into()).unwrap_or(JsValue::UNDEFINED);
+
+        let kind = get("type").as_string();
+        let version = get("version");
+
+        let Some(kind) = TYPES
+            .into_iter()
+            .find(|known| kind.as_deref() == Some(*known))
+        else {
+            return match kind {
+                Some(kind) if !version.is_undefined() => Err(ProtocolError::UnknownType(kind)),
+                _ if !version.is_undefined() => Err(ProtocolError::MissingField {
+                    message: "protocol",
+                    field: "type",
+                }),
+                _ => Ok(None),
+            };
+        };
+
+        let fields = Fields { data, kind };
+
+        let version = fields.f64("version")?;
+
+        if version != PROTOCOL_VERSION as f64 {
+            return Err(ProtocolError::UnsupportedVersion(version));
+        }
+
+        let message = match kind {
+            "READY" => Message::Ready,
+            "CANVAS" => Message::Canvas {
+                canvas: fields
+                    .get("canvas")?
+                    .dyn_into()
+                    .map_err(|_| fields.invalid("canvas", "an OffscreenCanvas"))?,
+                displays: fields.displays()?,
+            },
+            "RESIZE" => Message::Resize {
+                width: fields.u32("width")?,
+                height: fields.u32("height")?,
+                scale_factor: fields.f64("scaleFactor")?,
+            },
+            "PAUSE" => Message::Pause,
+            "RESUME" => Message::Resume,
+            "INIT" => Message::Init {
+                scaling: match fields.string("scaling")?.as_str() {
+                    "stretch" => Scaling::Stretch,
+                    "integer" => Scaling::Integer {
+                        width: fields.positive_u32("width")?,
+                        height: fields.positive_u32("height")?,
+                        border: match (
+                            fields.optional_string("borderColor")?,
+                            fields.optional_string("borderImage")?,
+                        ) {
+                            (_, Some(image)) => Border::Image(image),
+                            (Some(color), None) => Border::Color(color),
+                            (None, None) => Border::default(),
+                        },
+                    },
+                    _ => {
+                        return Err(fields.invalid("scaling", "\"stretch\" or \"integer\""));
+                    }
+                },
+            },
+            "LOG" => Message::Log {
+                level: match fields.string("level")?.as_str() {
+                    "error" => LogLevel::Error,
+                    "warn" => LogLevel::Warn,
+                    "info" => LogLevel::Info,
+                    "debug" => LogLevel::Debug,
+                    _ => {
+                        return Err(
+                            fields.invalid("level", "\"error\", \"warn\", \"info\" or \"debug\"")
+                        );
+                    }
+                },
+                message: fields.string("message")?,
+            },
+            "ERROR" => Message::Error {
+                kind: match fields.string("kind")?.as_str() {
+                    "init" => ErrorKind::Init,
+                    "runtime" => ErrorKind::Runtime,
+                    "panic" => ErrorKind::Panic,
+                    "unresponsive" => ErrorKind::Unresponsive,
+                    _ => {
+                        return Err(fields.invalid(
+                            "kind",
+                            "\"init\", \"runtime\", \"panic\" or \"unresponsive\"",
+                        ));
+                    }
+                },
+                message: fields.string("message")?,
+                location: fields.optional_string("location")?,
+                backtrace: fields.optional_string("backtrace")?,
+            },
+            "EXIT" => Message::Exit,
+            "RESTART" => Message::Restart,
+            "HEARTBEAT" => Message::Heartbeat {
+                frames: fields.u64("frames")?,
+                frame_time: fields.f64("frameTime")?,
+            },
+            _ => unreachable!("every entry of TYPES is handled"),
+        };
+
+        Ok(Some(message))
+    }
+
+    /// Builds the object to post for this message.
+    pub fn to_js(&self) -> Result<JsValue, JsValue> {
+        let message_object = Object::new();
+        let set = |key: &str, value: &JsValue| Reflect::set(&message_object, &key.into(), value);
+
+        set("type", &self.kind().into())?;
+        set("version", &PROTOCOL_VERSION.into())?;
+
+        match self {
+            Message::Canvas { canvas, displays } => {
+                set("canvas", canvas)?;
+
+                if !displays.is_empty() {
+                    let list = Array::new();
+
+                    for display in displays {
+                        let entry = Object::new();
+                        Reflect::set(&entry, &"name".into(), &(&display.name).into())?;
+                        Reflect::set(&entry, &"canvas".into(), &display.canvas)?;
+                        list.push(&entry);
+                    }
+
+                    set("displays", &list)?;
+                }
+            }
+            Message::Resize {
+                width,
+                height,
+                scale_factor,
+            } => {
+                set("width", &(*width).into())?;
+                set("height", &(*height).into())?;
+                set("scaleFactor", &(*scale_factor).into())?;
+            }
+            Message::Ready
+            | Message::Pause
+            | Message::Resume
+            | Message::Exit
+            | Message::Restart => {}
+            Message::Heartbeat { frames, frame_time } => {
+                set("frames", &(*frames as f64).into())?;
+                set("frameTime", &(*frame_time).into())?;
+            }
+            Message::Init { scaling } => match scaling {
+                Scaling::Stretch => {
+                    set("scaling", &"stretch".into())?;
+                }
+                Scaling::Integer {
+                    width,
+                    height,
+                    border,
+                } => {
+                    set("scaling", &"integer".into())?;
+                    set("width", &(*width).into())?;
+                    set("height", &(*height).into())?;
+
+                    match border {
+                        Border::Color(color) => set("borderColor", &color.into())?,
+                        Border::Image(url) => set("borderImage", &url.into())?,
+                    };
+                }
+            },
+            Message::Log { level, message } => {
+                let level = match level {
+                    LogLevel::Error => "error",
+                    LogLevel::Warn => "warn",
+                    LogLevel::Info => "info",
+                    LogLevel::Debug => "debug",
+                };
+
+                set("level", &level.into())?;
+                set("message", &message.into())?;
+            }
+            Message::Error {
+                kind,
+                message,
+                location,
+                backtrace,
+            } => {
+                let kind = match kind {
+                    ErrorKind::Init => "init",
+                    ErrorKind::Runtime => "runtime",
+                    ErrorKind::Panic => "panic",
+                    ErrorKind::Unresponsive => "unresponsive",
+                };
+
+                set("kind", &kind.into())?;
+                set("message", &message.into())?;
+
+                if let Some(location) = location {
+                    set("location", &location.into())?;
+                }
+
+                if let Some(backtrace) = backtrace {
+                    set("backtrace", &backtrace.into())?;
+                }
+            }
+        }
+
+        Ok(message_object.into())
+    }
+
+    /// Objects that have to be transferred, rather than copied, when posting this message.
+    pub fn transferables(&self) -> Array {
+        match self {
+            Message::Canvas { canvas, displays } => std::iter::once(canvas)
+                .chain(displays.iter().map(|display| &display.canvas))
+                .collect(),
+            _ => Array::new(),
+        }
+    }
+}
+
+/// Typed access to the fields of a message object, reporting errors against its `type`.
+struct Fields<'a> {
+    data: &'a JsValue,
+    kind: &'static str,
+}
+
+impl Fields<'_> {
+    fn get(&self, field: &'static str) -> Result<JsValue, ProtocolError> {
+        let value = Reflect::get(self.data, &field.into()).unwrap_or(JsValue::UNDEFINED);
+
+        if value.is_undefined() {
+            Err(ProtocolError::MissingField {
+                message: self.kind,
+                field,
+            })
+        } else {
+            Ok(value)
+        }
+    }
+
+    fn invalid(&self, field: &'static str, expected: &'static str) -> ProtocolError {
+        ProtocolError::InvalidField {
+            message: self.kind,
+            field,
+            expected,
+        }
+    }
+
+    fn f64(&self, field: &'static str) -> Result<f64, ProtocolError> {
+        self.get(field)?
+            .as_f64()
+            .ok_or_else(|| self.invalid(field, "a number"))
+    }
+
+    fn u32(&self, field: &'static str) -> Result<u32, ProtocolError> {
+        let value = self.f64(field)?;
+
+        if value >= 0.0 && value <= u32::MAX as f64 && value.fract() == 0.0 {
+            Ok(value as u32)
+        } else {
+            Err(self.invalid(field, "a non-negative whole number"))
+        }
+    }
+
+    /// Like [`Fields::u32`], but also rejects zero, for sizes that get divided by.
+    fn positive_u32(&self, field: &'static str) -> Result<u32, ProtocolError> {
+        match self.u32(field)? {
+            0 => Err(self.invalid(field, "a positive whole number")),
+            value => Ok(value),
+        }
+    }
+
+    fn u64(&self, field: &'static str) -> Result<u64, ProtocolError> {
+        let value = self.f64(field)?;
+
+        // Past 2^53 a JS number can't hold every whole number
+        if (0.0..=9_007_199_254_740_991.0).contains(&value) && value.fract() == 0.0 {
+            Ok(value as u64)
+        } else {
+            Err(self.invalid(field, "a non-negative whole number"))
+        }
+    }
+
+    fn string(&self, field: &'static str) -> Result<String, ProtocolError> {
+        self.get(field)?
+            .as_string()
+            .ok_or_else(|| self.invalid(field, "a string"))
+    }
+
+    /// Reads the optional list of secondary display canvases of a `CANVAS` message.
+    fn displays(&self) -> Result<Vec<NamedCanvas>, ProtocolError> {
+        let expected = "a list of { name, canvas } objects";
+
+        let list = match self.get("displays") {
+            Ok(list) => list,
+            Err(ProtocolError::MissingField { .. }) => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        let list = list
+            .dyn_into::<Array>()
+            .map_err(|_| self.invalid("displays", expected))?;
+
+        list.iter()
+            .map(|entry| {
+                let name = Reflect::get(&entry, &"name".into())
+                    .ok()
+                    .and_then(|name| name.as_string());
+                let canvas = Reflect::get(&entry, &"canvas".into())
+                    .ok()
+                    .and_then(|canvas| canvas.dyn_into::<OffscreenCanvas>().ok());
+
+                match (name, canvas) {
+                    (Some(name), Some(canvas)) => Ok(NamedCanvas { name, canvas }),
+                    _ => Err(self.invalid("displays", expected)),
+                }
+            })
+            .collect()
+    }
+
+    fn optional_string(&self, field: &'static str) -> Result<Option<String>, ProtocolError> {
+        match self.string(field) {
+            Ok(value) => Ok(Some(value)),
+            Err(ProtocolError::MissingField { .. }) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
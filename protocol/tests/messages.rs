@@ -0,0 +1,150 @@
+// The protocol works on JS values, so these only run on wasm:
+//
+//     cargo test -p rose-sample-rs-protocol --target wasm32-unknown-unknown
+//
+// with `wasm-bindgen-test-runner` from `wasm-bindgen-cli` set as the runner, see
+// `.cargo/config.toml`. They run in Node, which has no OffscreenCanvas, so CANVAS isn't covered.
+#![cfg(target_arch = "wasm32")]
+
+use js_sys::{Object, Reflect};
+use protocol::{Border, ErrorKind, LogLevel, Message, PROTOCOL_VERSION, ProtocolError, Scaling};
+use wasm_bindgen::JsValue;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+fn object(fields: &[(&str, JsValue)]) -> JsValue {
+    let object = Object::new();
+
+    for (key, value) in fields {
+        Reflect::set(&object, &(*key).into(), value).unwrap();
+    }
+
+    object.into()
+}
+
+#[wasm_bindgen_test]
+fn messages_round_trip() {
+    let messages = [
+        Message::Ready,
+        Message::Resize {
+            width: 1920,
+            height: 1080,
+            scale_factor: 1.5,
+        },
+        Message::Pause,
+        Message::Resume,
+        Message::Init {
+            scaling: Scaling::Integer {
+                width: 336,
+                height: 262,
+                border: Border::Image("border.png".to_string()),
+            },
+        },
+        Message::Log {
+            level: LogLevel::Warn,
+            message: "Careful".to_string(),
+        },
+        Message::Error {
+            kind: ErrorKind::Panic,
+            message: "Oh no".to_string(),
+            location: Some("src/lib.rs:1:1".to_string()),
+            backtrace: None,
+        },
+        Message::Exit,
+        Message::Restart,
+        Message::Heartbeat {
+            frames: 600,
+            frame_time: 16.7,
+        },
+    ];
+
+    for message in messages {
+        let sent = message.to_js().unwrap();
+
+        assert_eq!(Message::from_js(&sent), Ok(Some(message)));
+    }
+}
+
+#[wasm_bindgen_test]
+fn other_traffic_is_left_alone() {
+    let plugin = object(&[("type", "plugin_channel".into())]);
+
+    assert_eq!(Message::from_js(&plugin), Ok(None));
+    assert_eq!(Message::from_js(&"PAUSE".into()), Ok(None));
+}
+
+#[wasm_bindgen_test]
+fn unknown_types_are_rejected() {
+    let message = object(&[
+        ("type", "TELEPORT".into()),
+        ("version", PROTOCOL_VERSION.into()),
+    ]);
+
+    assert_eq!(
+        Message::from_js(&message),
+        Err(ProtocolError::UnknownType("TELEPORT".to_string()))
+    );
+}
+
+#[wasm_bindgen_test]
+fn missing_fields_are_rejected() {
+    let resize = object(&[
+        ("type", "RESIZE".into()),
+        ("version", PROTOCOL_VERSION.into()),
+        ("width", 640.into()),
+        ("scaleFactor", 1.into()),
+    ]);
+
+    assert_eq!(
+        Message::from_js(&resize),
+        Err(ProtocolError::MissingField {
+            message: "RESIZE",
+            field: "height",
+        })
+    );
+
+    // Every message of ours has to say which version it is from
+    let unversioned = object(&[("type", "PAUSE".into())]);
+
+    assert_eq!(
+        Message::from_js(&unversioned),
+        Err(ProtocolError::MissingField {
+            message: "PAUSE",
+            field: "version",
+        })
+    );
+}
+
+#[wasm_bindgen_test]
+fn other_versions_are_rejected() {
+    let message = object(&[
+        ("type", "PAUSE".into()),
+        ("version", (PROTOCOL_VERSION + 1).into()),
+    ]);
+
+    assert_eq!(
+        Message::from_js(&message),
+        Err(ProtocolError::UnsupportedVersion(
+            (PROTOCOL_VERSION + 1) as f64
+        ))
+    );
+}
+
+#[wasm_bindgen_test]
+fn integer_scaling_needs_a_size() {
+    let init = object(&[
+        ("type", "INIT".into()),
+        ("version", PROTOCOL_VERSION.into()),
+        ("scaling", "integer".into()),
+        ("width", 0.into()),
+        ("height", 262.into()),
+    ]);
+
+    assert!(matches!(
+        Message::from_js(&init),
+        Err(ProtocolError::InvalidField {
+            message: "INIT",
+            field: "width",
+            ..
+        })
+    ));
+}